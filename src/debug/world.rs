@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
-use crate::{player::{FreeViewMovment, Player}, voxel_world::chunk::{self, octree::Voxel, Chunk, CHUNK_SIZE}};
+use voxel_dream::voxel_world::{chunk::{self, octree::query::Frustum, Chunk}, floating_origin::{FloatingOrigin, WorldPosition}, settings::VoxelWorldSettings};
+use crate::player::{FreeViewMovment, Player};
 
 use super::DebugModeData;
 
@@ -24,16 +25,30 @@ pub fn draw_octree_borders(
     debug_mode_data: ResMut<DebugModeData>,
     mut debug_gizmos: Gizmos<DebugGizmos>,
     query: Query<&Chunk>,
-    player_query: Query<&Transform, With<FreeViewMovment>>
+    player_query: Query<(&WorldPosition, &Camera, &GlobalTransform), With<FreeViewMovment>>,
+    settings: Res<VoxelWorldSettings>,
+    origin: Res<FloatingOrigin>
 ) {
-    let transform = player_query.single();
+    let (player_position, camera, camera_transform) = player_query.single();
     if debug_mode_data.chunk_gizmos && debug_mode_data.in_debug_mode {
+        let view_projection = camera.projection_matrix() * camera_transform.compute_matrix().inverse();
+
         for chunk in query.iter() {
-            if chunk::coords_to_chunk_pos(transform.translation) == chunk.position {
-                for (voxel, position, size) in chunk.octree.voxel_iterator() {
-                    let mut pos = chunk::octree_to_world(chunk.octree.size, chunk.position, position);
+            let relative_position = chunk.position.wrapping_sub(origin.chunk);
+
+            if player_position.chunk == chunk.position {
+                // Only the leaves seen by the camera, the frustum is moved into octree coordinates
+                let octree_to_render = Mat4::from_scale_rotation_translation(
+                    Vec3::splat(settings.chunk_size / chunk.octree.cart_size() as f32),
+                    Quat::IDENTITY,
+                    chunk::chunk_pos_to_coords(&settings, relative_position),
+                );
+                let frustum = Frustum::from_view_projection((view_projection * octree_to_render).as_dmat4());
+
+                for (node, position) in chunk.octree.query().in_frustum(frustum) {
+                    let mut pos = chunk::octree_to_world(&settings, chunk.octree.size(), relative_position, position);
                 
-                    let world_size = chunk.octree.relative_size(size) * chunk::CHUNK_SIZE;
+                    let world_size = chunk.octree.relative_size(node.size()) * settings.chunk_size;
     
                     pos.x += world_size / 2.;
                     pos.y += world_size / 2.;
@@ -46,15 +61,15 @@ pub fn draw_octree_borders(
                 }
             }
 
-            let mut pos = chunk::chunk_pos_to_coords(chunk.position);
-            pos.x += CHUNK_SIZE /2.;
-            pos.y += CHUNK_SIZE /2.;
-            pos.z += CHUNK_SIZE /2.;
+            let mut pos = chunk::chunk_pos_to_coords(&settings, relative_position);
+            pos.x += settings.chunk_size /2.;
+            pos.y += settings.chunk_size /2.;
+            pos.z += settings.chunk_size /2.;
             
             debug_gizmos.cuboid(
-                Transform::from_translation(pos).with_scale(Vec3::splat(CHUNK_SIZE)),
+                Transform::from_translation(pos).with_scale(Vec3::splat(settings.chunk_size)),
                 Color::GREEN,
             );
         }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,3 @@
-mod voxel_world;
 mod player;
 mod debug;
 mod controls;
@@ -9,7 +8,7 @@ use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use debug::Debug;
 use controls::Controls;
-use voxel_world::VoxelWorld;
+use voxel_dream::voxel_world::VoxelWorld;
 use player::Player;
 
 fn main() { 
@@ -29,10 +28,10 @@ fn main() {
 fn cursor_settings(
     mut query: Query<&mut Window, With<PrimaryWindow>>
 ) {
-    let mut primary_window = query.single_mut();
-
-    //primary_window.cursor.grab_mode = CursorGrabMode::Confined;
-    primary_window.cursor.visible = false;
+    if let Ok(mut primary_window) = query.get_single_mut() {
+        //primary_window.cursor.grab_mode = CursorGrabMode::Confined;
+        primary_window.cursor.visible = false;
+    }
 }
 
 fn quit_game (
@@ -1,6 +1,7 @@
 use bevy::{input::mouse::MouseMotion, prelude::*};
 use std::f32::consts::PI;
-use crate::{controls::Controls, voxel_world::chunk_generator::ChunkGenerator};
+use voxel_dream::voxel_world::{chunk_generator::ChunkGenerator, floating_origin::{FloatingOriginCamera, WorldPosition}};
+use crate::controls::Controls;
 
 pub struct Player;
 
@@ -86,6 +87,8 @@ fn setup (
         ChunkGenerator {
             render_cube_size: 4,
         },
+        FloatingOriginCamera,
+        WorldPosition::default(),
         FreeViewMovment {
             move_speed: 5.,
             fast_move_speed: 20., 
@@ -1,15 +1,30 @@
+pub mod block_registry;
 pub mod chunk;
 pub mod chunk_generator;
+#[cfg(feature = "render")]
+pub mod chunk_mesher;
+pub mod diagnostics;
+pub mod floating_origin;
+pub mod settings;
 
 use bevy::prelude::*;
 
-use self::chunk_generator::ChunkGeneratorPlugin;
+use self::{block_registry::{BlockRegistry, BLOCKS_PATH}, chunk_generator::ChunkGeneratorPlugin, diagnostics::VoxelWorldDiagnosticsPlugin, floating_origin::FloatingOriginPlugin, settings::VoxelWorldSettings};
 
 pub struct VoxelWorld;
 
 impl Plugin for VoxelWorld {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(ChunkGeneratorPlugin);
+            .init_resource::<VoxelWorldSettings>()
+            .add_plugins((FloatingOriginPlugin, ChunkGeneratorPlugin, VoxelWorldDiagnosticsPlugin));
+
+        // A registry inserted before the plugin is kept
+        if !app.world.contains_resource::<BlockRegistry>() {
+            app.insert_resource(BlockRegistry::load_or_default(BLOCKS_PATH));
+        }
+
+        #[cfg(feature = "render")]
+        app.add_plugins(chunk_mesher::ChunkMesherPlugin);
     }
 }
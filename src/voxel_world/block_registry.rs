@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use super::chunk::octree::Voxel;
+
+/// Blocks shipped with the voxel world, used when the block data file can't be loaded.
+const DEFAULT_BLOCKS: &str = include_str!("../../assets/blocks.ron");
+
+/// Block data file loaded by the `VoxelWorld` plugin, relative to the working directory
+pub const BLOCKS_PATH: &str = "assets/blocks.ron";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockRegistryError {
+    Io(String),
+    Parse(String),
+    DuplicateId(u16),
+    DuplicateName(String),
+    /// The id 0 is the content of empty octrees, it must be a non solid block
+    MissingAir,
+}
+
+/// Texture paths of a block, relative to the assets folder.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockTextures {
+    pub top: String,
+    pub side: String,
+    pub bottom: String,
+}
+
+/// Entry of the block registry, as written in the block data files.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockProperties {
+    pub id: u16,
+    pub name: String,
+    pub solid: bool,
+    pub transparent: bool,
+    pub liquid: bool,
+    pub hardness: f32,
+    /// Light level emitted by the block, 0 if it does not emit light
+    pub light_emission: u8,
+    #[serde(default)]
+    pub textures: Option<BlockTextures>,
+}
+
+impl BlockProperties {
+    /// Whether the block has faces to mesh
+    pub fn is_visible(&self) -> bool {
+        self.solid || self.liquid
+    }
+
+    /// Whether the block hides the faces of the blocks behind it
+    pub fn is_opaque(&self) -> bool {
+        self.is_visible() && !self.transparent
+    }
+}
+
+/// Properties of the blocks stored as `Voxel` ids in the chunk octrees.
+#[derive(Resource, Debug, Clone)]
+pub struct BlockRegistry {
+    // Indexed by block id
+    blocks: Vec<Option<BlockProperties>>,
+    names: HashMap<String, Voxel>,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::from_ron_str(DEFAULT_BLOCKS).expect("Built-in block registry is invalid")
+    }
+}
+
+impl BlockRegistry {
+    pub fn new(blocks: Vec<BlockProperties>) -> Result<Self, BlockRegistryError> {
+        let mut registry = Self {
+            blocks: Vec::new(),
+            names: HashMap::new(),
+        };
+
+        for block in blocks {
+            let id = block.id as usize;
+            if registry.blocks.len() <= id {
+                registry.blocks.resize(id + 1, None);
+            }
+            if registry.blocks[id].is_some() {
+                return Err(BlockRegistryError::DuplicateId(block.id));
+            }
+            if registry.names.insert(block.name.clone(), Voxel(block.id)).is_some() {
+                return Err(BlockRegistryError::DuplicateName(block.name));
+            }
+            registry.blocks[id] = Some(block);
+        }
+
+        match registry.get(Voxel::EMPTY) {
+            Some(air) if !air.is_visible() => Ok(registry),
+            _ => Err(BlockRegistryError::MissingAir),
+        }
+    }
+
+    pub fn from_ron_str(data: &str) -> Result<Self, BlockRegistryError> {
+        let blocks: Vec<BlockProperties> = ron::from_str(data)
+            .map_err(|error| BlockRegistryError::Parse(error.to_string()))?;
+        Self::new(blocks)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BlockRegistryError> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|error| BlockRegistryError::Io(error.to_string()))?;
+        Self::from_ron_str(&data)
+    }
+
+    /// Registry of the data file at path, the built-in one if the file can't be loaded
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        Self::load(path.as_ref()).unwrap_or_else(|error| {
+            warn!("Using the built-in blocks, {} can't be loaded: {error:?}", path.as_ref().display());
+            Self::default()
+        })
+    }
+
+    pub fn get(&self, voxel: Voxel) -> Option<&BlockProperties> {
+        self.blocks.get(voxel.0 as usize)?.as_ref()
+    }
+
+    /// Id of the block with the given name
+    pub fn voxel(&self, name: &str) -> Option<Voxel> {
+        self.names.get(name).copied()
+    }
+
+    /// Whether the voxel has to be meshed, unknown ids are not
+    pub fn is_visible(&self, voxel: Voxel) -> bool {
+        self.get(voxel).is_some_and(BlockProperties::is_visible)
+    }
+
+    /// Whether the voxel hides the faces behind it, unknown ids don't
+    pub fn is_opaque(&self, voxel: Voxel) -> bool {
+        self.get(voxel).is_some_and(BlockProperties::is_opaque)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BlockProperties> {
+        self.blocks.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_registry() {
+        let registry = BlockRegistry::default();
+        let stone = registry.voxel("stone").unwrap();
+
+        assert_eq!(registry.voxel("air"), Some(Voxel::EMPTY));
+        assert_eq!(registry.get(stone).unwrap().name, "stone");
+        assert_eq!(registry.voxel("unknown"), None);
+        assert!(!registry.is_visible(Voxel::EMPTY));
+        assert!(registry.is_visible(stone));
+        assert!(!registry.is_visible(Voxel(u16::MAX)));
+        assert!(registry.is_visible(registry.voxel("water").unwrap()));
+        assert!(!registry.is_opaque(registry.voxel("water").unwrap()));
+        assert!(registry.is_opaque(stone));
+    }
+
+    #[test]
+    fn test_voxels_match_block_data_file() {
+        let registry = BlockRegistry::load(BLOCKS_PATH).unwrap();
+
+        assert_eq!(registry.voxel("dirt"), Some(Voxel::DIRT));
+        assert_eq!(registry.voxel("stone"), Some(Voxel::STONE));
+    }
+
+    #[test]
+    fn load_block_data_file() {
+        let loaded = BlockRegistry::load(BLOCKS_PATH).unwrap();
+        assert!(loaded.iter().eq(BlockRegistry::default().iter()));
+
+        assert!(matches!(BlockRegistry::load("assets/missing.ron"), Err(BlockRegistryError::Io(_))));
+        assert!(BlockRegistry::load_or_default("assets/missing.ron").iter().eq(BlockRegistry::default().iter()));
+    }
+
+    #[test]
+    fn invalid_registries() {
+        let air = "(id: 0, name: \"air\", solid: false, transparent: true, liquid: false, hardness: 0., light_emission: 0)";
+        let lamp = "(id: 1, name: \"lamp\", solid: true, transparent: false, liquid: false, hardness: 1., light_emission: 15)";
+
+        assert!(BlockRegistry::from_ron_str(&format!("[{air}, {lamp}]")).is_ok());
+        assert_eq!(BlockRegistry::from_ron_str(&format!("[{lamp}]")).unwrap_err(), BlockRegistryError::MissingAir);
+        assert_eq!(BlockRegistry::from_ron_str(&format!("[{air}, {lamp}, {lamp}]")).unwrap_err(), BlockRegistryError::DuplicateId(1));
+        assert!(matches!(BlockRegistry::from_ron_str("[(id: 0)]"), Err(BlockRegistryError::Parse(_))));
+    }
+}
@@ -1,48 +1,52 @@
 pub mod octree;
 
-use bevy::{math::{f32, I64Vec3}, prelude::*, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}};
+use bevy::{math::{f32, I64Vec3}, prelude::*};
+#[cfg(feature = "render")]
+use bevy::render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages};
 use self::octree::{Octree, OctreePosition, Voxel};
+#[cfg(feature = "render")]
+use self::octree::neighbour::NeighbourDirection;
+#[cfg(feature = "render")]
+use super::block_registry::BlockRegistry;
 
-use super::chunk_generator::world_generator::WorldGenerator;
-
-pub const CHUNK_SIZE: f32 = 10.;
+use super::{chunk_generator::world_generator::WorldGenerator, settings::VoxelWorldSettings};
 
 #[derive(Component, Debug)]
 pub struct Chunk {
     pub octree: Octree,
     pub position: I64Vec3,
-    pub mesh: Handle<Mesh>
 }
 
-pub fn octree_to_offset(size: u8, octree_pos: OctreePosition) -> Vec3 {
+pub fn octree_to_offset(settings: &VoxelWorldSettings, size: u8, octree_pos: OctreePosition) -> Vec3 {
     Vec3::new(
             octree_pos.0 as f32 / Octree::octree_size_to_cartestian(size) as f32, 
             octree_pos.1 as f32 / Octree::octree_size_to_cartestian(size) as f32, 
             octree_pos.2 as f32 / Octree::octree_size_to_cartestian(size) as f32
         ) 
-        * CHUNK_SIZE
+        * settings.chunk_size
 }
 
-pub fn octree_to_world(size: u8, position: I64Vec3, octree_pos: OctreePosition) -> Vec3 {
-    octree_to_offset(size, octree_pos) + chunk_pos_to_coords(position)
+pub fn octree_to_world(settings: &VoxelWorldSettings, size: u8, position: I64Vec3, octree_pos: OctreePosition) -> Vec3 {
+    octree_to_offset(settings, size, octree_pos) + chunk_pos_to_coords(settings, position)
 }
 
-pub fn coords_to_chunk_pos(coords: Vec3) -> I64Vec3 {
+pub fn coords_to_chunk_pos(settings: &VoxelWorldSettings, coords: Vec3) -> I64Vec3 {
     I64Vec3 {
-        x: (coords.x / CHUNK_SIZE).floor() as i64,
-        y: (coords.y / CHUNK_SIZE).floor() as i64,
-        z: (coords.z / CHUNK_SIZE).floor() as i64,
+        x: (coords.x / settings.chunk_size).floor() as i64,
+        y: (coords.y / settings.chunk_size).floor() as i64,
+        z: (coords.z / settings.chunk_size).floor() as i64,
     }
 }
 
-pub fn chunk_pos_to_coords(position: I64Vec3) -> Vec3 {
-    position.as_vec3() * CHUNK_SIZE
+pub fn chunk_pos_to_coords(settings: &VoxelWorldSettings, position: I64Vec3) -> Vec3 {
+    position.as_vec3() * settings.chunk_size
 }
 
-pub async fn generate_octree(position: I64Vec3, world_generator: &WorldGenerator) -> Octree {
-    let mut tree = Octree::new(world_generator.chunk_octree_size, None);
+//Settings must have been validated, see VoxelWorldSettings::validate
+pub async fn generate_octree(position: I64Vec3, world_generator: &WorldGenerator, settings: &VoxelWorldSettings, ground: Voxel) -> Octree {
+    let mut tree = Octree::new(settings.chunk_octree_size, None);
 
-    let delta = tree.size - world_generator.world_block_ocree_size;
+    let delta = settings.block_size_delta();
 
     let mut height_map: Vec<Vec<i128>> = Vec::with_capacity(delta as usize);
     
@@ -50,9 +54,9 @@ pub async fn generate_octree(position: I64Vec3, world_generator: &WorldGenerator
         height_map.push(Vec::with_capacity(Octree::octree_size_to_cartestian(delta) as usize));
         
         for j in 0..Octree::octree_size_to_cartestian(delta) as usize {
-            let map_pos = octree_to_world(delta, position, OctreePosition(i as u64, 0, j as u64)).xz();
-            let mut height = world_generator.get_world_height(map_pos) - chunk_pos_to_coords(position).y;
-            height /= CHUNK_SIZE;
+            let map_pos = octree_to_world(settings, delta, position, OctreePosition(i as u64, 0, j as u64)).xz();
+            let mut height = world_generator.get_world_height(map_pos) - chunk_pos_to_coords(settings, position).y;
+            height /= settings.chunk_size;
             height *= Octree::octree_size_to_cartestian(delta) as f32;
             height = height.clamp(0., Octree::octree_size_to_cartestian(delta) as f32);
             
@@ -60,21 +64,22 @@ pub async fn generate_octree(position: I64Vec3, world_generator: &WorldGenerator
         }
     }
 
-    tree.fill_with_heigh_map(height_map, world_generator.world_block_ocree_size).await;
+    tree.fill_with_heigh_map(height_map, settings.world_block_octree_size, ground).await;
     tree
 }
 
-pub async fn generate_mesh(tree: &Octree) -> Mesh {
+#[cfg(feature = "render")]
+pub async fn generate_mesh(tree: &Octree, settings: &VoxelWorldSettings, registry: &BlockRegistry) -> Mesh {
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
     .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32;3]>::new())
     .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0,Vec::<[f32;2]>::new())
     .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL,Vec::<[f32;3]>::new())
     .with_inserted_indices(Indices::U32(Vec::<u32>::new()));
     for (voxel, position, size) in tree.voxel_iterator() {
-        if voxel != Voxel::Empty {
-            let mut pos = octree_to_offset(tree.size, position);
+        if registry.is_visible(voxel) && !is_hidden(tree, registry, position, size) {
+            let mut pos = octree_to_offset(settings, tree.size(), position);
         
-            let world_size = tree.relative_size(size) * CHUNK_SIZE;
+            let world_size = tree.relative_size(size) * settings.chunk_size;
 
             pos.x += world_size / 2.;
             pos.y += world_size / 2.;
@@ -90,6 +95,16 @@ pub async fn generate_mesh(tree: &Octree) -> Mesh {
     mesh
 }
 
+// Leaves behind opaque blocks on their six faces can't be seen, the ones on the border of the octree may be seen from the adjacent chunk
+#[cfg(feature = "render")]
+fn is_hidden(tree: &Octree, registry: &BlockRegistry, position: OctreePosition, size: u8) -> bool {
+    NeighbourDirection::FACES.into_iter().all(|direction| match tree.neighbour(position, size, direction) {
+        Ok(neighbour) => neighbour.leaves().into_iter().all(|(voxel, _, _)| registry.is_opaque(voxel)),
+        Err(_) => false,
+    })
+}
+
+#[cfg(feature = "render")]
 pub fn get_cube_mesh() -> Mesh {
     Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
     .with_inserted_attribute(
@@ -206,3 +221,43 @@ pub fn get_cube_mesh() -> Mesh {
         20,21,23 , 21,22,23, // forward (-z)
     ]))
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::block_on;
+    use noise::Perlin;
+
+    use super::*;
+    use crate::voxel_world::block_registry::BlockRegistry;
+
+    #[test]
+    fn generated_octree_golden_hash() {
+        let world_generator = WorldGenerator {
+            perlin: Perlin::new(65464),
+            amplitude: 5.,
+            scale: 10.,
+            ground_block: "stone".to_string(),
+        };
+        let settings = VoxelWorldSettings::default();
+
+        let stone = BlockRegistry::default().voxel("stone").unwrap();
+
+        let tree = block_on(generate_octree(I64Vec3::new(0, -1, 0), &world_generator, &settings, stone));
+        assert_eq!(tree.stats().leaves.len(), 2);
+        // Update only when the generated terrain is meant to change
+        assert_eq!(tree.merkle_hash(), 2620484555624919324);
+    }
+
+    #[test]
+    #[cfg(feature = "render")]
+    fn mesh_skips_hidden_leaves() {
+        let registry = BlockRegistry::default();
+        let mut tree = Octree::new(3, registry.voxel("stone"));
+        // Transparent, the water does not hide the stone touching it but is hidden by it
+        tree.set_voxel(OctreePosition(2, 2, 2), 0, registry.voxel("water").unwrap()).unwrap();
+
+        let mesh = block_on(generate_mesh(&tree, &VoxelWorldSettings::default(), &registry));
+        // 22 leaves of 24 vertices, less the enclosed water and (3, 3, 2), (3, 2, 3), (2, 3, 3), (3, 3, 3)
+        assert_eq!(mesh.count_vertices(), (22 - 5) * 24);
+    }
+}
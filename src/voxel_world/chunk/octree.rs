@@ -1,30 +1,124 @@
-use std::{array::from_fn, future};
+pub mod arena;
+pub mod csg;
+pub mod dag;
+pub mod dense;
+pub mod diff;
+pub mod downsample;
+pub mod flood;
+pub mod merkle;
+pub mod neighbour;
+pub mod query;
+pub mod stats;
+pub mod transform;
+pub mod validate;
+
+use std::{array::from_fn, future, sync::Arc};
+
+use bevy::tasks::{AsyncComputeTaskPool, TaskPool};
+
+use self::merkle::MerkleCache;
+
+/// Content of a node, T is the payload stored in the leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OctreeContent<T = Voxel> {
+    Childs([Box<Octree<T>>; 8]),
+    Voxel(T)
+}
 
-use bevy::{render::{mesh::{Indices, Mesh, PrimitiveTopology}, render_asset::RenderAssetUsages}, utils::futures};
-use ::futures::future::join_all;
+/// Id of a block in the `BlockRegistry`, its properties are looked up in the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Voxel(pub u16);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum OctreeContent {
-    Childs([Box<Octree>; 8]),
-    Voxel(Voxel)
+impl Voxel {
+    /// Air, the registry always gives it the id 0
+    pub const EMPTY: Voxel = Voxel(0);
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Voxel {
-    Empty,
-    Dirt,
-    Stone,
+// Fixed ids of the octree tests, they must match the ids of dirt and stone in assets/blocks.ron.
+// The code outside of the tests looks the ids up by name with `BlockRegistry::voxel`
+#[cfg(test)]
+impl Voxel {
+    pub(crate) const DIRT: Voxel = Voxel(1);
+    pub(crate) const STONE: Voxel = Voxel(2);
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Position of a voxel of size 0 in an octree, the nodes of size n are at positions multiple of 2^n.
+/// Positions are ordered in Morton order, the order of `Octree::voxel_iterator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct OctreePosition(pub u64, pub u64, pub u64); 
 
+/// Largest coordinate + 1 that fits in a 64 bit Morton key, 21 bits per axis
+pub const MORTON_64_LIMIT: u64 = 1 << 21;
+/// Largest coordinate + 1 that fits in a 128 bit Morton key, 42 bits per axis
+pub const MORTON_128_LIMIT: u64 = 1 << 42;
+
 impl OctreePosition {
-    fn get_child_indice(&self, size: u8) -> usize {
+    /// Indice in its parent of the child of size `size` containing this position
+    pub fn get_child_indice(&self, size: u8) -> usize {
         (((self.0 >> size) & 1) + (((self.1 >> size) & 1) << 1) + (((self.2 >> size) & 1) << 2)).try_into().unwrap() // entre 0 et 7
     }
 
-    fn morton_increment(&mut self, size: u8) {
+    //Position of the child at indice of a cube at self, child_size being the size of the child
+    pub fn child(&self, indice: usize, child_size: u8) -> OctreePosition {
+        let indice = indice as u64;
+        OctreePosition(
+            self.0 | ((indice & 1) << child_size),
+            self.1 | (((indice >> 1) & 1) << child_size),
+            self.2 | (((indice >> 2) & 1) << child_size)
+        )
+    }
+
+    /// Position of the node of the given size containing this position
+    pub fn parent(&self, size: u8) -> OctreePosition {
+        let mask = !(Octree::octree_size_to_cartestian(size) - 1);
+        OctreePosition(self.0 & mask, self.1 & mask, self.2 & mask)
+    }
+
+    /// Smallest node containing both positions, as its position and size
+    pub fn common_ancestor(&self, other: &OctreePosition) -> (OctreePosition, u8) {
+        let differing_bits = (self.0 ^ other.0) | (self.1 ^ other.1) | (self.2 ^ other.2);
+        let size = (u64::BITS - differing_bits.leading_zeros()) as u8;
+        (self.parent(size), size)
+    }
+
+    /// Interleaves the coordinates as `...z1y1x1z0y0x0`, None if a coordinate is not below `MORTON_64_LIMIT`
+    pub fn morton_key(&self) -> Option<u64> {
+        if self.0 >= MORTON_64_LIMIT || self.1 >= MORTON_64_LIMIT || self.2 >= MORTON_64_LIMIT {
+            return None;
+        }
+        Some(spread_bits(self.0) | (spread_bits(self.1) << 1) | (spread_bits(self.2) << 2))
+    }
+
+    pub fn from_morton_key(key: u64) -> OctreePosition {
+        OctreePosition(compact_bits(key), compact_bits(key >> 1), compact_bits(key >> 2))
+    }
+
+    /// Same as `morton_key` over 128 bits, None if a coordinate is not below `MORTON_128_LIMIT`
+    pub fn morton_key_128(&self) -> Option<u128> {
+        if self.0 >= MORTON_128_LIMIT || self.1 >= MORTON_128_LIMIT || self.2 >= MORTON_128_LIMIT {
+            return None;
+        }
+        let mut key = 0;
+        for bit in 0..42 {
+            key |= (((self.0 >> bit) & 1) as u128) << (3 * bit);
+            key |= (((self.1 >> bit) & 1) as u128) << (3 * bit + 1);
+            key |= (((self.2 >> bit) & 1) as u128) << (3 * bit + 2);
+        }
+        Some(key)
+    }
+
+    pub fn from_morton_key_128(key: u128) -> OctreePosition {
+        let mut pos = OctreePosition(0, 0, 0);
+        for bit in 0..42 {
+            pos.0 |= (((key >> (3 * bit)) & 1) as u64) << bit;
+            pos.1 |= (((key >> (3 * bit + 1)) & 1) as u64) << bit;
+            pos.2 |= (((key >> (3 * bit + 2)) & 1) as u64) << bit;
+        }
+        pos
+    }
+
+    /// Moves to the next node of the given size in Morton order
+    pub fn morton_increment(&mut self, size: u8) {
         //Code incompréhensible pour incrémenter en cartésiennes selon un shema de Morton sans convertir
         let mut dif = self.0 & self.1 & self.2;
         dif = (dif + (1 << size)) ^ dif; // Tous les bits qui seront modifiés par l'addition de la puissance e.
@@ -40,49 +134,145 @@ impl OctreePosition {
     }
 }
 
+impl Ord for OctreePosition {
+    // Compares the axis holding the highest differing bit, z before y before x on equal bits,
+    // which works for any coordinates without building the keys
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let differing = [self.0 ^ other.0, self.1 ^ other.1, self.2 ^ other.2];
+        let mut axis = 2;
+        for candidate in [1, 0] {
+            if differing[axis] < differing[candidate] && differing[axis] < (differing[axis] ^ differing[candidate]) {
+                axis = candidate;
+            }
+        }
+        match axis {
+            0 => self.0.cmp(&other.0),
+            1 => self.1.cmp(&other.1),
+            _ => self.2.cmp(&other.2),
+        }
+    }
+}
+
+impl PartialOrd for OctreePosition {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+//Spreads the 21 lowest bits of value to every third bit
+fn spread_bits(value: u64) -> u64 {
+    let mut x = value & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
+}
+
+//Inverse of spread_bits
+fn compact_bits(key: u64) -> u64 {
+    let mut x = key & 0x1249_2492_4924_9249;
+    x = (x | x >> 2) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x >> 4) & 0x100f_00f0_0f00_f00f;
+    x = (x | x >> 8) & 0x1f_0000_ff00_00ff;
+    x = (x | x >> 16) & 0x1f_0000_0000_ffff;
+    x = (x | x >> 32) & 0x1f_ffff;
+    x
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum OctreeError {
     SizeLargerThanOctree,
     NotAVoxel,
     NotAnEdge,
     TooSmallToBeSplit,
+    SizeMismatch,
 }
 
+/// Octree over any payload, `Voxel` by default. Light levels, fluid amounts or block ids can be stored as well,
+/// leaves holding equal payloads are merged.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Octree {
-    pub size: u8,
-    pub content: OctreeContent,
+pub struct Octree<T = Voxel> {
+    // Private like content, a node resized in place would keep its stale hash_cache
+    size: u8,
+    // Private so that every edit goes through a method clearing hash_cache, see `Octree::content_mut`
+    content: OctreeContent<T>,
+    hash_cache: MerkleCache,
 
 }
 
 impl Octree {
     //Can panic if size>63
     pub fn new(size: u8, voxel: Option<Voxel>) -> Self {
+        Self::filled(size, voxel.unwrap_or(Voxel::EMPTY))
+    }
+
+    pub fn octree_size_to_cartestian(size: u8) -> u64 {
+        (2 as u64).pow(size as u32)
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    //Can panic if size>63
+    pub fn filled(size: u8, voxel: T) -> Self {
         if size > 63 {
             panic!("Max size is 63");
         }
+        Self::from_content(size, OctreeContent::Voxel(voxel))
+    }
+
+    /// Node split into childs, whose size must be size - 1
+    pub fn with_childs(size: u8, childs: [Box<Octree<T>>; 8]) -> Self {
+        Self::from_content(size, OctreeContent::Childs(childs))
+    }
+
+    /// Node holding content, the sizes are not checked, see `Octree::validate`
+    pub fn from_content(size: u8, content: OctreeContent<T>) -> Self {
         Self {
             size,
-            content: OctreeContent::Voxel(voxel.unwrap_or(Voxel::Empty))
+            content,
+            hash_cache: MerkleCache::default(),
         }
     }
 
-    pub fn voxel_iterator<'a>(&'a self) -> OctreeIterator<'a> {
+    /// Size of the node, its side is 2^size voxels
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    pub fn content(&self) -> &OctreeContent<T> {
+        &self.content
+    }
+
+    /// Content of the node to edit it in place, clears the cached hash of the node.
+    /// Its parents are cleared as well when reached through `content_mut` or `get_cube_mut`.
+    pub fn content_mut(&mut self) -> &mut OctreeContent<T> {
+        self.hash_cache.clear();
+        &mut self.content
+    }
+
+    pub fn voxel_iterator<'a>(&'a self) -> OctreeIterator<'a, T> {
         OctreeIterator::new(self)
     }
 
-    pub fn get_voxel(& self, pos: OctreePosition) -> Voxel {
+    #[cfg(any(test, feature = "bench"))]
+    pub fn lookup_voxel_iterator<'a>(&'a self) -> OctreeLookupIterator<'a, T> {
+        OctreeLookupIterator::new(self)
+    }
+
+    pub fn get_voxel(& self, pos: OctreePosition) -> T {
         let cube = self.get_cube(pos, 0).unwrap();
 
-        if let OctreeContent::Voxel(voxel) = cube.content {
-            voxel
+        if let OctreeContent::Voxel(ref voxel) = cube.content {
+            voxel.clone()
             
         } else {
             panic!("Get cube at size 0 did not return a voxel");
         }
     }
 
-    pub fn get_cube(& self, pos: OctreePosition, min_size: u8) -> Result<& Octree, OctreeError> {
+    pub fn get_cube(& self, pos: OctreePosition, min_size: u8) -> Result<& Octree<T>, OctreeError> {
         if min_size > self.size {
             return Err(OctreeError::SizeLargerThanOctree);
         }
@@ -101,17 +291,20 @@ impl Octree {
         Ok(current_cube)
     }
 
-    pub fn get_cube_mut(&mut self, pos: OctreePosition, min_size: u8)-> Result<& mut Octree, OctreeError> {
+    pub fn get_cube_mut(&mut self, pos: OctreePosition, min_size: u8)-> Result<& mut Octree<T>, OctreeError> {
         if min_size > self.size {
             return Err(OctreeError::SizeLargerThanOctree);
         }
         
         let mut current_size = self.size;
         let mut current_cube = self;
+        // The returned node may be edited, the hashes of its parents can't be kept
+        current_cube.hash_cache.clear();
         while min_size < current_size {
             if let OctreeContent::Childs(ref mut childs) = current_cube.content {
                 current_size -= 1;
                 current_cube = childs[pos.get_child_indice(current_size)].as_mut();
+                current_cube.hash_cache.clear();
             } else {
                 break;
             }
@@ -124,8 +317,9 @@ impl Octree {
         if self.size == 0 {
             return Err(OctreeError::TooSmallToBeSplit);
         }
-        if let OctreeContent::Voxel(voxel) = self.content {
-            let new_childs: [Box<Octree>; 8] = from_fn(|_i| Box::new(Octree::new(self.size - 1, Some(voxel))));
+        self.hash_cache.clear();
+        if let OctreeContent::Voxel(ref voxel) = self.content {
+            let new_childs: [Box<Octree<T>>; 8] = from_fn(|_i| Box::new(Octree::filled(self.size - 1, voxel.clone())));
             self.content = OctreeContent::Childs(new_childs);
 
             Ok(())
@@ -134,39 +328,63 @@ impl Octree {
         }
     }
 
-    pub fn set_voxel(&mut self, pos: OctreePosition, size: u8, voxel: Voxel) -> Result<(), OctreeError> {
-        let cube = self.get_cube_mut(pos, size)?;
-        
-        match cube.content {
-            OctreeContent::Childs(_) => {
-                cube.content = OctreeContent::Voxel(voxel);
+    /// Sets the cube of the given size at pos, then merges back the nodes along the edited path
+    /// whose eight childs ended up being the same voxel.
+    pub fn set_voxel(&mut self, pos: OctreePosition, size: u8, voxel: T) -> Result<(), OctreeError> {
+        self.set_voxel_node(pos, size, voxel)?;
+        self.debug_validate_path(pos, size);
+        Ok(())
+    }
+
+    fn set_voxel_node(&mut self, pos: OctreePosition, size: u8, voxel: T) -> Result<(), OctreeError> {
+        self.hash_cache.clear();
+        match size.cmp(&self.size) {
+            std::cmp::Ordering::Greater => return Err(OctreeError::SizeLargerThanOctree),
+            std::cmp::Ordering::Equal => {
+                self.content = OctreeContent::Voxel(voxel);
+                return Ok(());
             }
-            OctreeContent::Voxel(_) => {
-                match size.cmp(&cube.size) {
-                    std::cmp::Ordering::Less => {
-                        cube.split().unwrap();
-                        cube.set_voxel(pos, size, voxel)?;
-                    },
-                    std::cmp::Ordering::Equal => {
-                        cube.content = OctreeContent::Voxel(voxel);
-                    }
-                    std::cmp::Ordering::Greater => panic!("The fond cube size cannot be grater that size since it is a voxel"),
-                }
+            std::cmp::Ordering::Less => {}
+        }
+
+        if let OctreeContent::Voxel(ref current_voxel) = self.content {
+            if *current_voxel == voxel {
+                return Ok(());
             }
+            self.split()?;
         }
-        
+
+        let child_size = self.size - 1;
+        match self.content {
+            OctreeContent::Childs(ref mut childs) => childs[pos.get_child_indice(child_size)].set_voxel_node(pos, size, voxel)?,
+            OctreeContent::Voxel(_) => panic!("This node has just been splitted but does not have childs"),
+        }
+
+        self.merge_childs();
+
         Ok(())
     }
 
-    pub fn cart_size(&self) -> u64 {
-        Self::octree_size_to_cartestian(self.size)
+    //Turns the node into a voxel if its eight childs are the same voxel, returns true if it did
+    fn merge_childs(&mut self) -> bool {
+        self.hash_cache.clear();
+        if let OctreeContent::Childs(ref childs) = self.content {
+            if let OctreeContent::Voxel(ref voxel) = childs[0].content {
+                if childs.iter().all(|child| matches!(child.content, OctreeContent::Voxel(ref child_voxel) if child_voxel == voxel)) {
+                    self.content = OctreeContent::Voxel(voxel.clone());
+                    return true;
+                }
+            }
+        }
+        false
     }
 
-    pub fn octree_size_to_cartestian(size: u8) -> u64 {
-        (2 as u64).pow(size as u32)
+    pub fn cart_size(&self) -> u64 {
+        Octree::octree_size_to_cartestian(self.size)
     }
 
     pub fn optimize(& mut self) {
+        self.invalidate_hashes();
         let mut pos = OctreePosition(0, 0, 0);
         let limit =  self.cart_size();
         while pos.0 < limit && pos.1 < limit && pos.2 < limit {
@@ -178,7 +396,7 @@ impl Octree {
 
                 voxel = match deepest_cube.content {
                     OctreeContent::Childs(_) => panic!("Deepest cube can't have childs"),
-                    OctreeContent::Voxel(voxel) => voxel,
+                    OctreeContent::Voxel(ref voxel) => voxel.clone(),
                 };
                 deepest_cube_size = deepest_cube.size;
             }
@@ -187,12 +405,12 @@ impl Octree {
                 let mut all_childs_same_voxel = true;
                 let childs = match parent.content {
                     OctreeContent::Childs(ref mut childs) => childs,
-                    OctreeContent::Voxel(voxel) => panic!("Parents have childs"),
+                    OctreeContent::Voxel(_) => panic!("Parents have childs"),
                 };
     
                 for child in childs {
-                    if let OctreeContent::Voxel(child_voxel) = child.content {
-                        if child_voxel != voxel {
+                    if let OctreeContent::Voxel(ref child_voxel) = child.content {
+                        if *child_voxel != voxel {
                             all_childs_same_voxel = false
                         }
                     } else {
@@ -211,20 +429,64 @@ impl Octree {
         }
     }
 
-    pub async fn fill_with_heigh_map(& mut self, heigh_map: Vec<Vec<i128>>, block_size: u8) {
+    pub fn relative_size(&self, size: u8) -> f32 {
+        Octree::octree_size_to_cartestian(size) as f32 / Octree::octree_size_to_cartestian(self.size) as f32
+    }
+}
+
+// Nodes below the split top levels of a parallel height map fill, each filled by a task
+type FillFrontier<'a> = Vec<(&'a mut Octree, OctreePosition)>;
+
+/// Top levels split by `Octree::fill_with_heigh_map_parallel` before filling the nodes below them in parallel, 2 levels give up to 64 tasks
+pub const PARALLEL_FILL_LEVELS: u8 = 2;
+
+impl Octree {
+    /// Fills the octree with ground under the height map and empty voxels above it, on the current task
+    pub async fn fill_with_heigh_map(&mut self, heigh_map: Vec<Vec<i128>>, block_size: u8, ground: Voxel) {
+        self.fill_with_heigh_map_parallel(heigh_map, block_size, ground, 0).await;
+    }
+
+    /// `fill_with_heigh_map` with parallel_levels top levels split before spawning a task per node left to fill
+    /// on the `AsyncComputeTaskPool`, 0 fills the whole octree on the current task.
+    /// Opt-in as the tasks are only worth their overhead with several threads in the pool, see `benches/octree.rs`
+    pub async fn fill_with_heigh_map_parallel(&mut self, heigh_map: Vec<Vec<i128>>, block_size: u8, ground: Voxel, parallel_levels: u8) {
         let size_delta = self.size - block_size;
 
         assert_eq!(heigh_map.len(), Octree::octree_size_to_cartestian(size_delta) as usize, "Heigh map octree size and block size are not matching");
         assert_eq!(heigh_map[0].len(), Octree::octree_size_to_cartestian(size_delta) as usize, "Heigh map octree size and block size are not matching");
 
-        let (lowest_point_res, highest_point_res)  = Self::generate_res_high_maps(heigh_map, size_delta).await;
-
-        self.content = OctreeContent::Voxel(Voxel::Empty);
-        self.apply_res_heigh_map(block_size, OctreePosition(0, 0, 0), &lowest_point_res, &highest_point_res).await;
+        // Shared with the spawned tasks, which must own their data
+        let res_maps = Arc::new(Self::generate_res_high_maps(heigh_map, size_delta));
+        let (lowest_point_res, highest_point_res) = &*res_maps;
+
+        self.content = OctreeContent::Voxel(Voxel::EMPTY);
+        self.hash_cache.clear();
+
+        let mut frontier = Vec::new();
+        self.split_res_heigh_map(block_size, OctreePosition(0, 0, 0), lowest_point_res, highest_point_res, ground, parallel_levels, &mut frontier);
+
+        if !frontier.is_empty() {
+            let thread_pool = AsyncComputeTaskPool::get_or_init(TaskPool::default);
+            let tasks: Vec<_> = frontier.iter().map(|(node, pos)| {
+                let (size, pos, res_maps) = (node.size, *pos, res_maps.clone());
+                thread_pool.spawn(async move {
+                    let mut node = Octree::new(size, Some(Voxel::EMPTY));
+                    node.apply_res_heigh_map(block_size, pos, &res_maps.0, &res_maps.1, ground);
+                    node
+                })
+            }).collect();
+
+            // Awaiting the tasks does not block the thread, so chunks generated on the same pool cannot starve it
+            for ((node, _), task) in frontier.into_iter().zip(tasks) {
+                *node = task.await;
+            }
+            self.merge_top_levels(parallel_levels);
+        }
+        self.debug_validate(true);
 
     }
 
-    async fn generate_res_high_maps(heigh_map: Vec<Vec<i128>>, heigh_map_size: u8) -> (Vec<Vec<Vec<i128>>>, Vec<Vec<Vec<i128>>>) {
+    fn generate_res_high_maps(heigh_map: Vec<Vec<i128>>, heigh_map_size: u8) -> (Vec<Vec<Vec<i128>>>, Vec<Vec<Vec<i128>>>) {
 
         let mut highest_point_res: Vec<Vec<Vec<i128>>> = Vec::with_capacity(heigh_map_size as usize + 1);
         let mut lowest_point_res:  Vec<Vec<Vec<i128>>> = Vec::with_capacity(heigh_map_size as usize + 1);
@@ -267,66 +529,142 @@ impl Octree {
         (lowest_point_res, highest_point_res)
     }
 
-    //Can panic if Octree is not empty
-    async fn apply_res_heigh_map(&mut self, block_size: u8, pos: OctreePosition, lowest_point_res: &Vec<Vec<Vec<i128>>>, highest_point_res: &Vec<Vec<Vec<i128>>>) {
+    // Sets the node to a single voxel when the height map allows it, returns false when it has to be split
+    fn apply_res_heigh_map_leaf(&mut self, block_size: u8, pos: OctreePosition, lowest_point_res: &[Vec<Vec<i128>>], highest_point_res: &[Vec<Vec<i128>>], ground: Voxel) -> bool {
         if block_size == self.size {
             let height = highest_point_res[0][pos.0 as usize][pos.2 as usize];
             if height > pos.1 as i128 {
-                self.content = OctreeContent::Voxel(Voxel::Stone);
+                self.content = OctreeContent::Voxel(ground);
             } else if height <= pos.1 as i128  {
-                self.content = OctreeContent::Voxel(Voxel::Empty);
+                self.content = OctreeContent::Voxel(Voxel::EMPTY);
             }
+            return true;
+        }
+
+        let delta_size = self.size - block_size;
+        let map_x = pos.0 / (2 as u64).pow(delta_size as u32);
+        let map_y = pos.2 / (2 as u64).pow(delta_size as u32);
+
+        let highest_point = highest_point_res[delta_size as usize][map_x as usize][map_y as usize];
+        let lowest_point = lowest_point_res[delta_size as usize][map_x as usize][map_y as usize];
+
+        if highest_point <= pos.1 as i128 {
+            self.content = OctreeContent::Voxel(Voxel::EMPTY);
+        } else if lowest_point >= pos.1 as i128 + (2 as i128).pow(delta_size as u32) {
+            self.content = OctreeContent::Voxel(ground);
         } else {
-            let delta_size = self.size - block_size;
-            let map_x = pos.0 / (2 as u64).pow(delta_size as u32);
-            let map_y = pos.2 / (2 as u64).pow(delta_size as u32);
+            return false;
+        }
+        true
+    }
 
-            let highest_point = highest_point_res[delta_size as usize][map_x as usize][map_y as usize];
-            let lowest_point = lowest_point_res[delta_size as usize][map_x as usize][map_y as usize];
+    //Can panic if Octree is not empty
+    fn apply_res_heigh_map(&mut self, block_size: u8, pos: OctreePosition, lowest_point_res: &[Vec<Vec<i128>>], highest_point_res: &[Vec<Vec<i128>>], ground: Voxel) {
+        if self.apply_res_heigh_map_leaf(block_size, pos, lowest_point_res, highest_point_res, ground) {
+            return;
+        }
 
-            if highest_point <= pos.1 as i128 {
-                self.content = OctreeContent::Voxel(Voxel::Empty);
-            } else if lowest_point >= pos.1 as i128 + (2 as i128).pow(delta_size as u32) {
-                self.content = OctreeContent::Voxel(Voxel::Stone);
-            } else {
-                self.split().unwrap();
+        let delta_size = self.size - block_size;
+        self.split().unwrap();
+        let childs = match self.content {
+            OctreeContent::Childs(ref mut childs) => childs,
+            OctreeContent::Voxel(_) => panic!("This node has just been splitted but does not have childs"),
+        };
 
-                let mut childs: &mut [Box<Octree>] = match self.content {
-                    OctreeContent::Childs(ref mut childs) => childs,
-                    OctreeContent::Voxel(_) => panic!("This node has just been splitted but does not have childs"),
-                };
+        for (indice, child) in childs.iter_mut().enumerate() {
+            child.apply_res_heigh_map(block_size, pos.child(indice, delta_size - 1), lowest_point_res, highest_point_res, ground);
+        }
+        self.merge_childs();
+    }
 
-                let mut child_pos = pos;
-                let mut futures = Vec::with_capacity(8);
-                for _ in 0..8 {
-                    let (child, remaining_childs) = childs.split_first_mut().unwrap();
-                    childs =  remaining_childs;
-                    let future = child.apply_res_heigh_map(block_size, child_pos, lowest_point_res, highest_point_res);
-                    futures.push(future);
-                    child_pos.morton_increment(delta_size - 1);
-                }
+    // Like apply_res_heigh_map on the top levels, the nodes left to fill below them are pushed to frontier
+    #[allow(clippy::too_many_arguments)]
+    fn split_res_heigh_map<'a>(&'a mut self, block_size: u8, pos: OctreePosition, lowest_point_res: &[Vec<Vec<i128>>], highest_point_res: &[Vec<Vec<i128>>], ground: Voxel, levels: u8, frontier: &mut FillFrontier<'a>) {
+        if self.apply_res_heigh_map_leaf(block_size, pos, lowest_point_res, highest_point_res, ground) {
+            return;
+        }
+        if levels == 0 {
+            frontier.push((self, pos));
+            return;
+        }
 
-                join_all(futures).await;
-            }
+        let delta_size = self.size - block_size;
+        self.split().unwrap();
+        let childs = match self.content {
+            OctreeContent::Childs(ref mut childs) => childs,
+            OctreeContent::Voxel(_) => panic!("This node has just been splitted but does not have childs"),
+        };
+
+        for (indice, child) in childs.iter_mut().enumerate() {
+            child.split_res_heigh_map(block_size, pos.child(indice, delta_size - 1), lowest_point_res, highest_point_res, ground, levels - 1, frontier);
         }
     }
 
-    pub fn relative_size(&self, size: u8) -> f32 {
-        Octree::octree_size_to_cartestian(size) as f32 / Octree::octree_size_to_cartestian(self.size) as f32
+    // Merges the nodes of the top levels bottom up, once the nodes below them are filled
+    fn merge_top_levels(&mut self, levels: u8) {
+        if levels == 0 {
+            return;
+        }
+        if let OctreeContent::Childs(ref mut childs) = self.content {
+            for child in childs.iter_mut() {
+                child.merge_top_levels(levels - 1);
+            }
+            self.merge_childs();
+        }
     }
+}
+
+
+/// Iterates over the leaves of an octree in Morton order, keeping the nodes left to visit on a stack.
+pub struct OctreeIterator<'a, T = Voxel> {
+    stack: Vec<(&'a Octree<T>, OctreePosition)>,
+}
+
+impl <'a, T> OctreeIterator<'a, T> {
+    pub fn new(octree: &'a Octree<T>) -> Self {
+        // At most 7 pending siblings per level plus the node being visited
+        let mut stack = Vec::with_capacity(octree.size as usize * 7 + 1);
+        stack.push((octree, OctreePosition(0, 0, 0)));
 
+        OctreeIterator { 
+            stack
+        }
+    }
 }
 
+impl<'a, T: Clone> Iterator for OctreeIterator<'a, T> {
+    type Item = (T, OctreePosition, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (cube, pos) = self.stack.pop()?;
+
+            match cube.content {
+                OctreeContent::Voxel(ref voxel) => return Some((voxel.clone(), pos, cube.size)),
+                OctreeContent::Childs(ref childs) => {
+                    // Pushed in reverse so the child with the lowest indice is visited first
+                    for (indice, child) in childs.iter().enumerate().rev() {
+                        self.stack.push((child.as_ref(), pos.child(indice, child.size)));
+                    }
+                }
+            }
+        }
+    }
+}
 
-pub struct OctreeIterator<'a> {
+/// Previous leaf iterator looking each leaf up from the root, O(n·depth) over the whole tree.
+/// Kept as a reference for `OctreeIterator` in tests and benchmarks, only built with the `bench` feature outside of tests.
+#[cfg(any(test, feature = "bench"))]
+pub struct OctreeLookupIterator<'a, T = Voxel> {
     pos: OctreePosition,
-    octree: &'a Octree,
+    octree: &'a Octree<T>,
     limit: u64
 }
 
-impl <'a> OctreeIterator<'a> {
-    pub fn new(octree: &'a Octree) -> Self {
-        OctreeIterator { 
+#[cfg(any(test, feature = "bench"))]
+impl <'a, T: Clone + Eq> OctreeLookupIterator<'a, T> {
+    pub fn new(octree: &'a Octree<T>) -> Self {
+        OctreeLookupIterator { 
             pos: OctreePosition(0, 0, 0), 
             octree: octree,
             limit: octree.cart_size()
@@ -334,8 +672,9 @@ impl <'a> OctreeIterator<'a> {
     }
 }
 
-impl<'a> Iterator for OctreeIterator<'a> {
-    type Item = (Voxel, OctreePosition, u8);
+#[cfg(any(test, feature = "bench"))]
+impl<'a, T: Clone + Eq> Iterator for OctreeLookupIterator<'a, T> {
+    type Item = (T, OctreePosition, u8);
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.pos.0 >= self.limit || self.pos.1 >= self.limit|| self.pos.2 >= self.limit {
@@ -350,7 +689,7 @@ impl<'a> Iterator for OctreeIterator<'a> {
 
         let voxel = match deepest_cube.content {
             OctreeContent::Childs(_) => panic!("Deepest cube can't have childs"),
-            OctreeContent::Voxel(voxel) => voxel,
+            OctreeContent::Voxel(ref voxel) => voxel.clone(),
         };
 
         Some((voxel, previous_pos, deepest_cube.size))
@@ -388,6 +727,60 @@ mod tests {
         assert_eq!(pos, OctreePosition(0, 2, 0));
     }
 
+    #[test]
+    fn morton_keys() {
+        let mut pos = OctreePosition(0, 0, 0);
+        for key in 0..4096 {
+            assert_eq!(pos.morton_key(), Some(key));
+            assert_eq!(pos.morton_key_128(), Some(key as u128));
+            assert_eq!(OctreePosition::from_morton_key(key), pos);
+            pos.morton_increment(0);
+        }
+
+        let far = OctreePosition(MORTON_64_LIMIT - 1, 123456, 1);
+        assert_eq!(OctreePosition::from_morton_key(far.morton_key().unwrap()), far);
+        assert_eq!(far.morton_key().map(u128::from), far.morton_key_128());
+        assert_eq!(OctreePosition(MORTON_64_LIMIT, 0, 0).morton_key(), None);
+
+        let farther = OctreePosition(MORTON_128_LIMIT - 1, 5, MORTON_64_LIMIT);
+        assert_eq!(OctreePosition::from_morton_key_128(farther.morton_key_128().unwrap()), farther);
+        assert_eq!(OctreePosition(0, MORTON_128_LIMIT, 0).morton_key_128(), None);
+    }
+
+    #[test]
+    fn morton_order() {
+        let positions = [
+            OctreePosition(3, 0, 7), OctreePosition(6, 5, 1), OctreePosition(0, 0, 0),
+            OctreePosition(7, 7, 7), OctreePosition(4, 0, 0), OctreePosition(1, 2, 3),
+        ];
+        for a in positions {
+            for b in positions {
+                assert_eq!(a.cmp(&b), a.morton_key().cmp(&b.morton_key()));
+            }
+        }
+        // Beyond 128 bit keys the comparison still works
+        assert!(OctreePosition(1 << 60, 0, 0) < OctreePosition(0, 1 << 60, 0));
+        assert!(OctreePosition((1 << 62) - 1, (1 << 62) - 1, 0) < OctreePosition(0, 0, 1 << 62));
+
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(8, 0, 8), 2, Voxel::DIRT).unwrap();
+        let leaves: Vec<OctreePosition> = tree.voxel_iterator().map(|(_, pos, _)| pos).collect();
+        assert!(leaves.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn parents_and_common_ancestor() {
+        let pos = OctreePosition(5, 6, 3);
+
+        assert_eq!(pos.parent(0), pos);
+        assert_eq!(pos.parent(2), OctreePosition(4, 4, 0));
+        assert_eq!(pos.parent(2).child(pos.get_child_indice(1), 1), pos.parent(1));
+        assert_eq!(pos.common_ancestor(&pos), (pos, 0));
+        assert_eq!(pos.common_ancestor(&OctreePosition(4, 7, 2)), (OctreePosition(4, 6, 2), 1));
+        assert_eq!(pos.common_ancestor(&OctreePosition(8, 0, 0)), (OctreePosition(0, 0, 0), 4));
+    }
+
     #[test]
     fn get_child_indice_test() {
         let pos = OctreePosition(2, 5, 1);
@@ -404,43 +797,16 @@ mod tests {
 
     #[test]
     fn test_octree_equal() {
-        let tree_1 = Octree{
-            size: 8,
-            content: OctreeContent::Childs([
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-            ]),
-        };
+        let tree_1 = Octree::from_content(8, OctreeContent::Childs([
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+        ]));
 
         let tree_2 = tree_1.clone();
 
@@ -449,10 +815,10 @@ mod tests {
 
     #[test]
     fn basic_octree_creation() {
-        let tree = Octree::new(5, Some(Voxel::Empty));
+        let tree = Octree::new(5, Some(Voxel::EMPTY));
 
         match tree.content {
-            OctreeContent::Voxel(Voxel::Empty) => {
+            OctreeContent::Voxel(Voxel::EMPTY) => {
                 return;
             }
             _ => {
@@ -463,7 +829,7 @@ mod tests {
 
     #[test]
     fn octree_split() {
-        let mut tree = Octree::new(8, Some(Voxel::Empty));
+        let mut tree = Octree::new(8, Some(Voxel::EMPTY));
 
         tree.split().unwrap();
 
@@ -474,7 +840,7 @@ mod tests {
             OctreeContent::Childs(ref childs) => {
                 for child in childs {
                     if let OctreeContent::Voxel(voxel) = child.content {
-                        if voxel != Voxel::Empty {
+                        if voxel != Voxel::EMPTY {
                             panic!("Splitted voxel has changed type");
                         }
                     } else {
@@ -487,54 +853,92 @@ mod tests {
 
     #[test]
     fn octree_access() {
-        let mut tree = Octree::new(8, Some(Voxel::Stone));
+        let mut tree = Octree::new(8, Some(Voxel::STONE));
 
         if let OctreeContent::Voxel(voxel) = tree.get_cube(OctreePosition(4, 4, 1), 0).unwrap().content {
-            assert_eq!(Voxel::Stone, voxel);
+            assert_eq!(Voxel::STONE, voxel);
         } else {
             panic!("Failed to access tree");
         }
 
-        assert_eq!(Voxel::Stone, tree.get_voxel(OctreePosition(2, 4, 1)));
+        assert_eq!(Voxel::STONE, tree.get_voxel(OctreePosition(2, 4, 1)));
 
         tree.split().unwrap();
 
-        assert_eq!(Voxel::Stone, tree.get_voxel(OctreePosition(2, 7, 1)));
+        assert_eq!(Voxel::STONE, tree.get_voxel(OctreePosition(2, 7, 1)));
     }
 
     #[test]
     fn octree_set_complex() {
-        let mut tree = Octree::new(8, Some(Voxel::Empty));
+        let mut tree = Octree::new(8, Some(Voxel::EMPTY));
 
-        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::Stone).unwrap();
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
 
 
-        assert_eq!(Voxel::Stone, tree.get_voxel(OctreePosition(2, 5, 1)));
+        assert_eq!(Voxel::STONE, tree.get_voxel(OctreePosition(2, 5, 1)));
 
-        assert_eq!(Voxel::Empty, tree.get_voxel(OctreePosition(2, 5, 2)));
-        assert_eq!(Voxel::Empty, tree.get_voxel(OctreePosition(3, 5, 1)));
-        assert_eq!(Voxel::Empty, tree.get_voxel(OctreePosition(4, 5, 1)));
-        assert_eq!(Voxel::Empty, tree.get_voxel(OctreePosition(2, 7, 1)));
-        assert_eq!(Voxel::Empty, tree.get_voxel(OctreePosition(2, 0, 1)));
+        assert_eq!(Voxel::EMPTY, tree.get_voxel(OctreePosition(2, 5, 2)));
+        assert_eq!(Voxel::EMPTY, tree.get_voxel(OctreePosition(3, 5, 1)));
+        assert_eq!(Voxel::EMPTY, tree.get_voxel(OctreePosition(4, 5, 1)));
+        assert_eq!(Voxel::EMPTY, tree.get_voxel(OctreePosition(2, 7, 1)));
+        assert_eq!(Voxel::EMPTY, tree.get_voxel(OctreePosition(2, 0, 1)));
 
-        let mut tree = Octree::new(8, Some(Voxel::Empty));
+        let mut tree = Octree::new(8, Some(Voxel::EMPTY));
 
-        tree.set_voxel(OctreePosition(0, 0, 0), 7, Voxel::Stone).unwrap();
+        tree.set_voxel(OctreePosition(0, 0, 0), 7, Voxel::STONE).unwrap();
         
-        assert_eq!(Voxel::Stone, tree.get_voxel(OctreePosition(10, 10, 10)));
+        assert_eq!(Voxel::STONE, tree.get_voxel(OctreePosition(10, 10, 10)));
+    }
+
+    #[test]
+    fn octree_set_merges_edited_path() {
+        let mut tree = Octree::new(8, Some(Voxel::EMPTY));
+
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::EMPTY).unwrap();
+        assert_eq!(tree, Octree::new(8, Some(Voxel::EMPTY)));
+
+        for indice in 0..8 {
+            let pos = OctreePosition(0, 0, 0).child(indice, 2);
+            tree.set_voxel(pos, 2, Voxel::DIRT).unwrap();
+        }
+        assert_eq!(tree.get_cube(OctreePosition(0, 0, 0), 0).unwrap().size, 3);
+        assert_eq!(tree.get_voxel(OctreePosition(7, 7, 7)), Voxel::DIRT);
+
+        tree.set_voxel(OctreePosition(0, 0, 0), 0, Voxel::DIRT).unwrap();
+        assert_eq!(tree.get_cube(OctreePosition(0, 0, 0), 0).unwrap().size, 3);
+
+        assert!(tree.set_voxel(OctreePosition(0, 0, 0), 9, Voxel::DIRT).is_err());
+    }
+
+    #[test]
+    fn generic_payload_octree() {
+        let mut light_levels: Octree<u8> = Octree::filled(4, 15);
+
+        light_levels.set_voxel(OctreePosition(3, 2, 1), 0, 7).unwrap();
+        assert_eq!(light_levels.get_voxel(OctreePosition(3, 2, 1)), 7);
+        assert_eq!(light_levels.get_voxel(OctreePosition(3, 2, 2)), 15);
+        assert!(light_levels.voxel_iterator().eq(light_levels.lookup_voxel_iterator()));
+
+        light_levels.set_voxel(OctreePosition(3, 2, 1), 0, 15).unwrap();
+        assert_eq!(light_levels, Octree::filled(4, 15));
+
+        let mut names: Octree<String> = Octree::filled(2, String::from("air"));
+        names.set_voxel(OctreePosition(0, 0, 0), 1, String::from("water")).unwrap();
+        assert_eq!(names.voxel_iterator().filter(|(name, _, _)| name == "water").count(), 1);
     }
 
     #[test]
     fn octree_iterator() {
-        let mut tree = Octree::new(8, Some(Voxel::Empty));
+        let mut tree = Octree::new(8, Some(Voxel::EMPTY));
 
-        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::Stone).unwrap();
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
 
         let mut len = 0;
         for (voxel, pos, _size) in tree.voxel_iterator() {
             len += 1;
             println!("{:?}", (voxel, pos, _size));
-            if voxel == Voxel::Stone {
+            if voxel == Voxel::STONE {
                 assert_eq!(pos, OctreePosition(2, 5, 1));
             }
         }
@@ -542,6 +946,40 @@ mod tests {
         assert_eq!(len, 7 * 7 + 8);
     }
 
+    #[test]
+    fn octree_iterator_matches_lookup_iterator() {
+        let mut tree = Octree::new(6, Some(Voxel::EMPTY));
+
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(32, 0, 16), 4, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(63, 63, 63), 0, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(8, 40, 24), 3, Voxel::STONE).unwrap();
+
+        assert!(tree.voxel_iterator().eq(tree.lookup_voxel_iterator()));
+
+        let tree = Octree::new(6, Some(Voxel::STONE));
+        assert!(tree.voxel_iterator().eq(tree.lookup_voxel_iterator()));
+        assert_eq!(tree.voxel_iterator().count(), 1);
+
+        let heigh_map: Vec<Vec<i128>> = (0..16).map(|i| (0..16).map(|j| (i * 7 + j * 3) % 16).collect()).collect();
+        let mut tree = Octree::new(8, None);
+        block_on(tree.fill_with_heigh_map(heigh_map, 4, Voxel::STONE));
+        assert!(tree.voxel_iterator().eq(tree.lookup_voxel_iterator()));
+    }
+
+    #[test]
+    fn parallel_heigh_map_fill_matches_sequential() {
+        let heigh_map: Vec<Vec<i128>> = (0..32).map(|i| (0..32).map(|j| (i * 5 + j * 11) % 32).collect()).collect();
+
+        let mut sequential = Octree::new(7, None);
+        block_on(sequential.fill_with_heigh_map_parallel(heigh_map.clone(), 2, Voxel::STONE, 0));
+        for parallel_levels in [1, PARALLEL_FILL_LEVELS, 10] {
+            let mut parallel = Octree::new(7, None);
+            block_on(parallel.fill_with_heigh_map_parallel(heigh_map.clone(), 2, Voxel::STONE, parallel_levels));
+            assert_eq!(parallel, sequential);
+        }
+    }
+
     #[test]
     fn res_high_map_generatrion() {
         let heigh_map: Vec<Vec<i128>> = vec![
@@ -555,7 +993,7 @@ mod tests {
             vec![2, 3, 2, 1, 0, 1, 3, 4],
         ];
 
-        let (lowest_point_res, highest_point_res) = block_on(Octree::generate_res_high_maps(heigh_map.clone(), 3));
+        let (lowest_point_res, highest_point_res) = Octree::generate_res_high_maps(heigh_map.clone(), 3);
 
         println!("Highest point: {highest_point_res:?}");
         assert_eq!(highest_point_res, vec![
@@ -601,13 +1039,13 @@ mod tests {
             vec![0, 0, 0, 0]
         ];
 
-        let (lowest_point_res, highest_point_res) = block_on(Octree::generate_res_high_maps(heigh_map, 2));
+        let (lowest_point_res, highest_point_res) = Octree::generate_res_high_maps(heigh_map, 2);
 
-        block_on(tree.apply_res_heigh_map(6, OctreePosition(0, 0, 0), &highest_point_res, &lowest_point_res));
+        tree.apply_res_heigh_map(6, OctreePosition(0, 0, 0), &highest_point_res, &lowest_point_res, Voxel::STONE);
 
         match tree.content {
             OctreeContent::Childs(_) => panic!(),
-            OctreeContent::Voxel(voxel) => assert_eq!(voxel, Voxel::Empty),
+            OctreeContent::Voxel(voxel) => assert_eq!(voxel, Voxel::EMPTY),
         }
     }
 
@@ -622,13 +1060,13 @@ mod tests {
             vec![4, 4, 4, 4],
         ];
 
-        let (lowest_point_res, highest_point_res) = block_on(Octree::generate_res_high_maps(heigh_map.clone(), 2));
+        let (lowest_point_res, highest_point_res) = Octree::generate_res_high_maps(heigh_map.clone(), 2);
 
-        block_on(tree.apply_res_heigh_map(6, OctreePosition(0, 0, 0), &highest_point_res, &lowest_point_res));
+        tree.apply_res_heigh_map(6, OctreePosition(0, 0, 0), &highest_point_res, &lowest_point_res, Voxel::STONE);
 
         match tree.content {
             OctreeContent::Childs(_) => panic!(),
-            OctreeContent::Voxel(voxel) => assert_ne!(voxel, Voxel::Empty),
+            OctreeContent::Voxel(voxel) => assert_ne!(voxel, Voxel::EMPTY),
         }
     }
     
@@ -641,47 +1079,20 @@ mod tests {
             vec![0, 0],
         ];
 
-        let (lowest_point_res, highest_point_res) = block_on(Octree::generate_res_high_maps(heigh_map.clone(), 1));
+        let (lowest_point_res, highest_point_res) = Octree::generate_res_high_maps(heigh_map.clone(), 1);
 
-        block_on(test_tree.apply_res_heigh_map(7, OctreePosition(0, 0, 0), &lowest_point_res, &highest_point_res));
+        test_tree.apply_res_heigh_map(7, OctreePosition(0, 0, 0), &lowest_point_res, &highest_point_res, Voxel::STONE);
         
-        let mut result_tree = Octree{
-            size: 8,
-            content: OctreeContent::Childs([
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Stone)
-                }),
-                Box::new(Octree { 
-                    size: 7, 
-                    content: OctreeContent::Voxel(Voxel::Empty)
-                }),
-            ]),
-        };
+        let mut result_tree = Octree::from_content(8, OctreeContent::Childs([
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::STONE))),
+            Box::new(Octree::from_content(7, OctreeContent::Voxel(Voxel::EMPTY))),
+        ]));
 
         assert_eq!(result_tree, test_tree);
     }
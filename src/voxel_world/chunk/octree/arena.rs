@@ -0,0 +1,331 @@
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+/// Node of an `ArenaOctree`, childs are stored as 8 contiguous nodes starting at the given index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaContent {
+    Childs(usize),
+    Voxel(Voxel),
+}
+
+/// Copy of a node returned by `ArenaOctree::get_cube`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaCube {
+    pub index: usize,
+    pub size: u8,
+    pub content: ArenaContent,
+}
+
+/// Octree storing its nodes in a single `Vec` instead of boxing every child.
+/// Blocks of 8 childs freed by `set_voxel` or `optimize` are reused by later splits.
+#[derive(Debug, Clone)]
+pub struct ArenaOctree {
+    pub size: u8,
+    nodes: Vec<ArenaContent>,
+    free_blocks: Vec<usize>,
+}
+
+const ROOT: usize = 0;
+
+impl ArenaOctree {
+    //Can panic if size>63
+    pub fn new(size: u8, voxel: Option<Voxel>) -> Self {
+        if size > 63 {
+            panic!("Max size is 63");
+        }
+        Self {
+            size,
+            nodes: vec![ArenaContent::Voxel(voxel.unwrap_or(Voxel::EMPTY))],
+            free_blocks: Vec::new(),
+        }
+    }
+
+    pub fn voxel_iterator<'a>(&'a self) -> ArenaOctreeIterator<'a> {
+        ArenaOctreeIterator::new(self)
+    }
+
+    /// Number of nodes in use, without the freed ones waiting to be reused
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free_blocks.len() * 8
+    }
+
+    /// Number of nodes allocated in the arena, including the freed ones
+    pub fn capacity(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn get_voxel(&self, pos: OctreePosition) -> Voxel {
+        let cube = self.get_cube(pos, 0).unwrap();
+
+        if let ArenaContent::Voxel(voxel) = cube.content {
+            voxel
+        } else {
+            panic!("Get cube at size 0 did not return a voxel");
+        }
+    }
+
+    pub fn get_cube(&self, pos: OctreePosition, min_size: u8) -> Result<ArenaCube, OctreeError> {
+        if min_size > self.size {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        let mut current_size = self.size;
+        let mut current_index = ROOT;
+        while min_size < current_size {
+            if let ArenaContent::Childs(first_child) = self.nodes[current_index] {
+                current_size -= 1;
+                current_index = first_child + pos.get_child_indice(current_size);
+            } else {
+                break;
+            }
+        }
+
+        Ok(ArenaCube {
+            index: current_index,
+            size: current_size,
+            content: self.nodes[current_index],
+        })
+    }
+
+    /// Splits the deepest cube containing pos whose size is at least size, like `Octree::split` on `Octree::get_cube_mut`
+    pub fn split(&mut self, pos: OctreePosition, size: u8) -> Result<(), OctreeError> {
+        let cube = self.get_cube(pos, size)?;
+        self.split_node(cube.index, cube.size)
+    }
+
+    fn split_node(&mut self, index: usize, size: u8) -> Result<(), OctreeError> {
+        if size == 0 {
+            return Err(OctreeError::TooSmallToBeSplit);
+        }
+        if let ArenaContent::Voxel(voxel) = self.nodes[index] {
+            let first_child = self.allocate_block(voxel);
+            self.nodes[index] = ArenaContent::Childs(first_child);
+
+            Ok(())
+        } else {
+            Err(OctreeError::NotAVoxel)
+        }
+    }
+
+    /// Sets the cube of the given size at pos and merges the edited path like `Octree::set_voxel`
+    pub fn set_voxel(&mut self, pos: OctreePosition, size: u8, voxel: Voxel) -> Result<(), OctreeError> {
+        if size > self.size {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        self.set_voxel_node(ROOT, self.size, pos, size, voxel)
+    }
+
+    fn set_voxel_node(&mut self, index: usize, node_size: u8, pos: OctreePosition, size: u8, voxel: Voxel) -> Result<(), OctreeError> {
+        if node_size == size {
+            self.replace_node(index, voxel);
+            return Ok(());
+        }
+
+        if let ArenaContent::Voxel(current_voxel) = self.nodes[index] {
+            if current_voxel == voxel {
+                return Ok(());
+            }
+            self.split_node(index, node_size)?;
+        }
+
+        if let ArenaContent::Childs(first_child) = self.nodes[index] {
+            self.set_voxel_node(first_child + pos.get_child_indice(node_size - 1), node_size - 1, pos, size, voxel)?;
+
+            let childs = &self.nodes[first_child..first_child + 8];
+            if childs.iter().all(|child| *child == ArenaContent::Voxel(voxel)) {
+                self.replace_node(index, voxel);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn optimize(&mut self) {
+        self.optimize_node(ROOT);
+    }
+
+    //Returns the voxel of the node if it is a leaf after optimization
+    fn optimize_node(&mut self, index: usize) -> Option<Voxel> {
+        match self.nodes[index] {
+            ArenaContent::Voxel(voxel) => Some(voxel),
+            ArenaContent::Childs(first_child) => {
+                let mut childs_voxel = [None; 8];
+                for (i, child_voxel) in childs_voxel.iter_mut().enumerate() {
+                    *child_voxel = self.optimize_node(first_child + i);
+                }
+
+                match childs_voxel[0] {
+                    Some(voxel) if childs_voxel.iter().all(|child_voxel| *child_voxel == Some(voxel)) => {
+                        self.replace_node(index, voxel);
+                        Some(voxel)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub fn cart_size(&self) -> u64 {
+        Octree::octree_size_to_cartestian(self.size)
+    }
+
+    fn allocate_block(&mut self, voxel: Voxel) -> usize {
+        match self.free_blocks.pop() {
+            Some(first_child) => {
+                self.nodes[first_child..first_child + 8].fill(ArenaContent::Voxel(voxel));
+                first_child
+            }
+            None => {
+                let first_child = self.nodes.len();
+                self.nodes.extend([ArenaContent::Voxel(voxel); 8]);
+                first_child
+            }
+        }
+    }
+
+    //Replaces a node by a leaf, giving its descendants back to the free list
+    fn replace_node(&mut self, index: usize, voxel: Voxel) {
+        if let ArenaContent::Childs(first_child) = self.nodes[index] {
+            for i in 0..8 {
+                self.replace_node(first_child + i, voxel);
+            }
+            self.free_blocks.push(first_child);
+        }
+        self.nodes[index] = ArenaContent::Voxel(voxel);
+    }
+
+    fn push_octree(&mut self, index: usize, octree: &Octree) {
+        match octree.content {
+            OctreeContent::Voxel(voxel) => self.nodes[index] = ArenaContent::Voxel(voxel),
+            OctreeContent::Childs(ref childs) => {
+                let first_child = self.allocate_block(Voxel::EMPTY);
+                self.nodes[index] = ArenaContent::Childs(first_child);
+                for (i, child) in childs.iter().enumerate() {
+                    self.push_octree(first_child + i, child);
+                }
+            }
+        }
+    }
+
+    fn to_octree_node(&self, index: usize, size: u8) -> Octree {
+        match self.nodes[index] {
+            ArenaContent::Voxel(voxel) => Octree::new(size, Some(voxel)),
+            ArenaContent::Childs(first_child) => Octree::with_childs(size, std::array::from_fn(|i| Box::new(self.to_octree_node(first_child + i, size - 1)))),
+        }
+    }
+}
+
+impl From<&Octree> for ArenaOctree {
+    fn from(octree: &Octree) -> Self {
+        let mut arena = ArenaOctree::new(octree.size, None);
+        arena.push_octree(ROOT, octree);
+        arena
+    }
+}
+
+impl From<&ArenaOctree> for Octree {
+    fn from(arena: &ArenaOctree) -> Self {
+        let octree = arena.to_octree_node(ROOT, arena.size);
+        octree.debug_validate(false);
+        octree
+    }
+}
+
+impl PartialEq for ArenaOctree {
+    // Compares the trees and not how their nodes are laid out in the arena
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.voxel_iterator().eq(other.voxel_iterator())
+    }
+}
+
+impl Eq for ArenaOctree {}
+
+/// Iterates over the leaves of an `ArenaOctree` in Morton order, like `OctreeIterator`.
+pub struct ArenaOctreeIterator<'a> {
+    stack: Vec<(usize, u8, OctreePosition)>,
+    octree: &'a ArenaOctree,
+}
+
+impl<'a> ArenaOctreeIterator<'a> {
+    pub fn new(octree: &'a ArenaOctree) -> Self {
+        let mut stack = Vec::with_capacity(octree.size as usize * 7 + 1);
+        stack.push((ROOT, octree.size, OctreePosition(0, 0, 0)));
+
+        ArenaOctreeIterator {
+            stack,
+            octree,
+        }
+    }
+}
+
+impl<'a> Iterator for ArenaOctreeIterator<'a> {
+    type Item = (Voxel, OctreePosition, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, size, pos) = self.stack.pop()?;
+
+            match self.octree.nodes[index] {
+                ArenaContent::Voxel(voxel) => return Some((voxel, pos, size)),
+                ArenaContent::Childs(first_child) => {
+                    for indice in (0..8).rev() {
+                        self.stack.push((first_child + indice, size - 1, pos.child(indice, size - 1)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited_octree() -> Octree {
+        let mut tree = Octree::new(6, Some(Voxel::EMPTY));
+
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(32, 0, 16), 4, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(63, 63, 63), 0, Voxel::DIRT).unwrap();
+        tree
+    }
+
+    #[test]
+    fn arena_conversion_is_lossless() {
+        let tree = edited_octree();
+        let arena = ArenaOctree::from(&tree);
+
+        assert_eq!(Octree::from(&arena), tree);
+        assert!(arena.voxel_iterator().eq(tree.voxel_iterator()));
+    }
+
+    #[test]
+    fn arena_matches_boxed_octree() {
+        let tree = edited_octree();
+
+        let mut arena = ArenaOctree::new(6, Some(Voxel::EMPTY));
+        arena.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        arena.set_voxel(OctreePosition(32, 0, 16), 4, Voxel::DIRT).unwrap();
+        arena.set_voxel(OctreePosition(63, 63, 63), 0, Voxel::DIRT).unwrap();
+
+        assert_eq!(Octree::from(&arena), tree);
+        assert_eq!(arena.get_voxel(OctreePosition(2, 5, 1)), Voxel::STONE);
+        assert_eq!(arena.get_voxel(OctreePosition(40, 10, 20)), Voxel::DIRT);
+        assert_eq!(arena.get_cube(OctreePosition(40, 10, 20), 0).unwrap().size, 4);
+    }
+
+    #[test]
+    fn arena_optimize_reuses_blocks() {
+        let mut arena = ArenaOctree::new(6, Some(Voxel::EMPTY));
+        arena.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        let capacity = arena.capacity();
+
+        arena.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::EMPTY).unwrap();
+        arena.optimize();
+        assert_eq!(arena.node_count(), 1);
+        assert_eq!(arena, ArenaOctree::new(6, Some(Voxel::EMPTY)));
+
+        arena.set_voxel(OctreePosition(60, 5, 1), 0, Voxel::STONE).unwrap();
+        assert_eq!(arena.capacity(), capacity);
+    }
+}
@@ -0,0 +1,399 @@
+use bevy::math::{DVec2, DVec3, Vec3Swizzles};
+
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+/// How a shape covers the cube of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    Inside,
+    Outside,
+    Partial,
+}
+
+/// Region of an octree, in octree coordinates where a voxel of size 0 is one unit wide.
+pub trait Shape {
+    /// Overlap of the shape with the cube going from min to max
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap;
+
+    /// Used for voxels of size 0 partially covered by the shape
+    fn contains(&self, point: DVec3) -> bool;
+}
+
+/// Axis-aligned box going from min to max.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxShape {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Shape for BoxShape {
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap {
+        if min.cmpge(self.min).all() && max.cmple(self.max).all() {
+            Overlap::Inside
+        } else if max.cmple(self.min).any() || min.cmpge(self.max).any() {
+            Overlap::Outside
+        } else {
+            Overlap::Partial
+        }
+    }
+
+    fn contains(&self, point: DVec3) -> bool {
+        point.cmpge(self.min).all() && point.cmplt(self.max).all()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    pub center: DVec3,
+    pub radius: f64,
+}
+
+impl Shape for Sphere {
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap {
+        let nearest = self.center.clamp(min, max);
+        let farthest = DVec3::select(self.center.cmplt((min + max) / 2.), max, min);
+
+        if self.center.distance_squared(farthest) <= self.radius * self.radius {
+            Overlap::Inside
+        } else if self.center.distance_squared(nearest) >= self.radius * self.radius {
+            Overlap::Outside
+        } else {
+            Overlap::Partial
+        }
+    }
+
+    fn contains(&self, point: DVec3) -> bool {
+        self.center.distance_squared(point) < self.radius * self.radius
+    }
+}
+
+/// Cylinder along the Y axis, going up from base for height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cylinder {
+    pub base: DVec3,
+    pub radius: f64,
+    pub height: f64,
+}
+
+impl Shape for Cylinder {
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap {
+        let (bottom, top) = (self.base.y, self.base.y + self.height);
+        let center = self.base.xz();
+        let (min_xz, max_xz) = (min.xz(), max.xz());
+
+        let nearest = center.clamp(min_xz, max_xz);
+        let farthest = DVec2::select(center.cmplt((min_xz + max_xz) / 2.), max_xz, min_xz);
+
+        if max.y <= bottom || min.y >= top || center.distance_squared(nearest) >= self.radius * self.radius {
+            Overlap::Outside
+        } else if min.y >= bottom && max.y <= top && center.distance_squared(farthest) <= self.radius * self.radius {
+            Overlap::Inside
+        } else {
+            Overlap::Partial
+        }
+    }
+
+    fn contains(&self, point: DVec3) -> bool {
+        point.y >= self.base.y && point.y < self.base.y + self.height
+            && self.base.xz().distance_squared(point.xz()) < self.radius * self.radius
+    }
+}
+
+/// Shape given by a signed distance function with a Lipschitz bound, `|f(a) - f(b)| <= lipschitz * |a - b|`, negative inside.
+/// Distances do not have to be exact, scaled or approximate fields only need a larger bound.
+pub struct LipschitzSdf<F: Fn(DVec3) -> f64> {
+    pub sdf: F,
+    pub lipschitz: f64,
+}
+
+impl<F: Fn(DVec3) -> f64> LipschitzSdf<F> {
+    /// Shape of a 1-Lipschitz sdf, like an exact distance function
+    pub fn new(sdf: F) -> Self {
+        Self { sdf, lipschitz: 1. }
+    }
+}
+
+impl<F: Fn(DVec3) -> f64> Shape for LipschitzSdf<F> {
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap {
+        // Largest change of the function between the center and a corner of the cube
+        let bound = self.lipschitz * min.distance(max) / 2.;
+        let distance = (self.sdf)((min + max) / 2.);
+
+        if distance <= -bound {
+            Overlap::Inside
+        } else if distance >= bound {
+            Overlap::Outside
+        } else {
+            Overlap::Partial
+        }
+    }
+
+    fn contains(&self, point: DVec3) -> bool {
+        (self.sdf)(point) < 0.
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsgOperation {
+    Union,
+    Subtract,
+    Intersect,
+}
+
+impl CsgOperation {
+    //Result of the operation on a node of self whose whole volume is other_voxel in the other tree, None leaves it unchanged
+    fn apply(&self, other_voxel: Voxel) -> Option<Voxel> {
+        match (self, other_voxel) {
+            (CsgOperation::Union, Voxel::EMPTY) => None,
+            (CsgOperation::Union, voxel) => Some(voxel),
+            (CsgOperation::Subtract, Voxel::EMPTY) => None,
+            (CsgOperation::Subtract, _) => Some(Voxel::EMPTY),
+            (CsgOperation::Intersect, Voxel::EMPTY) => Some(Voxel::EMPTY),
+            (CsgOperation::Intersect, _) => None,
+        }
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Sets every voxel inside shape, whole nodes are assigned when they are fully inside
+    /// and only the partially covered ones are split.
+    pub fn fill_shape(&mut self, shape: &impl Shape, voxel: T) {
+        self.fill_shape_node(OctreePosition(0, 0, 0), shape, voxel);
+    }
+
+    pub fn fill_box(&mut self, min: DVec3, max: DVec3, voxel: T) {
+        self.fill_shape(&BoxShape { min, max }, voxel);
+    }
+
+    pub fn fill_sphere(&mut self, center: DVec3, radius: f64, voxel: T) {
+        self.fill_shape(&Sphere { center, radius }, voxel);
+    }
+
+    pub fn fill_cylinder(&mut self, base: DVec3, radius: f64, height: f64, voxel: T) {
+        self.fill_shape(&Cylinder { base, radius, height }, voxel);
+    }
+
+    pub fn fill_sdf(&mut self, sdf: impl Fn(DVec3) -> f64, voxel: T) {
+        self.fill_shape(&LipschitzSdf::new(sdf), voxel);
+    }
+
+    // Returns whether the node was written, the hash caches of the nodes left as they were are kept
+    fn fill_shape_node(&mut self, pos: OctreePosition, shape: &impl Shape, voxel: T) -> bool {
+        if matches!(self.content, OctreeContent::Voxel(ref current_voxel) if *current_voxel == voxel) {
+            return false;
+        }
+
+        let min = DVec3::new(pos.0 as f64, pos.1 as f64, pos.2 as f64);
+        let max = min + self.cart_size() as f64;
+
+        let written = match shape.overlap(min, max) {
+            Overlap::Inside => {
+                self.content = OctreeContent::Voxel(voxel);
+                true
+            }
+            Overlap::Outside => false,
+            Overlap::Partial if self.size == 0 => {
+                let inside = shape.contains((min + max) / 2.);
+                if inside {
+                    self.content = OctreeContent::Voxel(voxel);
+                }
+                inside
+            }
+            Overlap::Partial => {
+                let split = matches!(self.content, OctreeContent::Voxel(_));
+                if split {
+                    self.split().unwrap();
+                }
+                let mut written = false;
+                if let OctreeContent::Childs(ref mut childs) = self.content {
+                    for (indice, child) in childs.iter_mut().enumerate() {
+                        written |= child.fill_shape_node(pos.child(indice, child.size), shape, voxel.clone());
+                    }
+                }
+                // A split leaf none of whose childs was written is merged back
+                if written || split {
+                    self.merge_childs();
+                }
+                written
+            }
+        };
+
+        if written {
+            self.hash_cache.clear();
+        }
+        written
+    }
+}
+
+impl Octree {
+    /// Octree filled with voxel where the 1-Lipschitz sdf is negative and empty elsewhere.
+    /// It is built top-down from the sdf at the node centers, only the nodes the surface may cross are split.
+    pub fn from_sdf(size: u8, sdf: impl Fn(DVec3) -> f64, voxel: Voxel) -> Self {
+        Self::from_lipschitz_sdf(size, sdf, 1., voxel)
+    }
+
+    /// Same as `from_sdf` for a sdf whose Lipschitz bound is lipschitz
+    pub fn from_lipschitz_sdf(size: u8, sdf: impl Fn(DVec3) -> f64, lipschitz: f64, voxel: Voxel) -> Self {
+        let mut tree = Octree::new(size, Some(Voxel::EMPTY));
+        tree.fill_shape(&LipschitzSdf { sdf, lipschitz }, voxel);
+        tree
+    }
+
+    /// Copies every non empty voxel of other into self
+    pub fn union(&mut self, other: &Octree) -> Result<(), OctreeError> {
+        self.combine(other, CsgOperation::Union)
+    }
+
+    /// Empties every voxel of self that is not empty in other
+    pub fn subtract(&mut self, other: &Octree) -> Result<(), OctreeError> {
+        self.combine(other, CsgOperation::Subtract)
+    }
+
+    /// Empties every voxel of self that is empty in other
+    pub fn intersect(&mut self, other: &Octree) -> Result<(), OctreeError> {
+        self.combine(other, CsgOperation::Intersect)
+    }
+
+    fn combine(&mut self, other: &Octree, operation: CsgOperation) -> Result<(), OctreeError> {
+        if self.size != other.size {
+            return Err(OctreeError::SizeMismatch);
+        }
+
+        self.combine_node(other, operation);
+        Ok(())
+    }
+
+    fn combine_node(&mut self, other: &Octree, operation: CsgOperation) {
+        self.hash_cache.clear();
+        match other.content {
+            OctreeContent::Voxel(other_voxel) => {
+                if let Some(voxel) = operation.apply(other_voxel) {
+                    self.content = OctreeContent::Voxel(voxel);
+                }
+            }
+            OctreeContent::Childs(ref other_childs) => {
+                // Subtracting from or intersecting with empty space leaves it empty
+                if self.content == OctreeContent::Voxel(Voxel::EMPTY) && operation != CsgOperation::Union {
+                    return;
+                }
+                if let OctreeContent::Voxel(_) = self.content {
+                    self.split().unwrap();
+                }
+                if let OctreeContent::Childs(ref mut childs) = self.content {
+                    for (child, other_child) in childs.iter_mut().zip(other_childs.iter()) {
+                        child.combine_node(other_child, operation);
+                    }
+                }
+                self.merge_childs();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_aligned_box_assigns_whole_nodes() {
+        let mut tree = Octree::new(6, Some(Voxel::EMPTY));
+        tree.fill_box(DVec3::new(0., 0., 0.), DVec3::splat(32.), Voxel::STONE);
+
+        let mut expected = Octree::new(6, Some(Voxel::EMPTY));
+        expected.set_voxel(OctreePosition(0, 0, 0), 5, Voxel::STONE).unwrap();
+        assert_eq!(tree, expected);
+
+        tree.fill_box(DVec3::ZERO, DVec3::splat(64.), Voxel::DIRT);
+        assert_eq!(tree, Octree::new(6, Some(Voxel::DIRT)));
+    }
+
+    #[test]
+    fn fill_unaligned_box() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.fill_box(DVec3::new(3., 1., 2.), DVec3::new(9., 4., 7.), Voxel::STONE);
+
+        let volume: u64 = tree.voxel_iterator()
+            .filter(|(voxel, _, _)| *voxel == Voxel::STONE)
+            .map(|(_, _, size)| Octree::octree_size_to_cartestian(size).pow(3))
+            .sum();
+        assert_eq!(volume, 6 * 3 * 5);
+        assert_eq!(tree.get_voxel(OctreePosition(3, 1, 2)), Voxel::STONE);
+        assert_eq!(tree.get_voxel(OctreePosition(9, 1, 2)), Voxel::EMPTY);
+    }
+
+    #[test]
+    fn fill_sphere_matches_sdf() {
+        let center = DVec3::splat(16.);
+        let mut sphere = Octree::new(5, Some(Voxel::EMPTY));
+        sphere.fill_sphere(center, 10., Voxel::STONE);
+
+        let mut sdf = Octree::new(5, Some(Voxel::EMPTY));
+        sdf.fill_sdf(|point| point.distance(center) - 10., Voxel::STONE);
+
+        assert_eq!(sphere, sdf);
+        assert_eq!(sphere.get_voxel(OctreePosition(16, 16, 16)), Voxel::STONE);
+        assert_eq!(sphere.get_voxel(OctreePosition(16, 27, 16)), Voxel::EMPTY);
+    }
+
+    #[test]
+    fn octree_from_sdf() {
+        let center = DVec3::new(16., 12., 20.);
+        let mut sphere = Octree::new(5, Some(Voxel::EMPTY));
+        sphere.fill_sphere(center, 9., Voxel::STONE);
+
+        let evaluations = std::cell::Cell::new(0);
+        let from_sdf = Octree::from_sdf(5, |point| {
+            evaluations.set(evaluations.get() + 1);
+            point.distance(center) - 9.
+        }, Voxel::STONE);
+        assert_eq!(from_sdf, sphere);
+        // Only the nodes near the surface are evaluated, not each of the 32^3 voxels
+        assert!(evaluations.get() < 32 * 32 * 32 / 2);
+
+        // A field three times steeper needs a three times larger bound to give the same tree
+        let scaled = Octree::from_lipschitz_sdf(5, |point| 3. * (point.distance(center) - 9.), 3., Voxel::STONE);
+        assert_eq!(scaled, sphere);
+    }
+
+    #[test]
+    fn fill_cylinder() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.fill_cylinder(DVec3::new(16., 4., 16.), 6., 10., Voxel::DIRT);
+
+        assert_eq!(tree.get_voxel(OctreePosition(16, 4, 16)), Voxel::DIRT);
+        assert_eq!(tree.get_voxel(OctreePosition(16, 13, 20)), Voxel::DIRT);
+        assert_eq!(tree.get_voxel(OctreePosition(16, 14, 16)), Voxel::EMPTY);
+        assert_eq!(tree.get_voxel(OctreePosition(23, 8, 16)), Voxel::EMPTY);
+    }
+
+    #[test]
+    fn csg_operations() {
+        let mut first = Octree::new(5, Some(Voxel::EMPTY));
+        first.fill_box(DVec3::ZERO, DVec3::new(16., 32., 32.), Voxel::STONE);
+        let mut second = Octree::new(5, Some(Voxel::EMPTY));
+        second.fill_sphere(DVec3::splat(16.), 8., Voxel::DIRT);
+
+        let mut union = first.clone();
+        union.union(&second).unwrap();
+        assert_eq!(union.get_voxel(OctreePosition(20, 16, 16)), Voxel::DIRT);
+        assert_eq!(union.get_voxel(OctreePosition(2, 2, 2)), Voxel::STONE);
+
+        let mut subtract = first.clone();
+        subtract.subtract(&second).unwrap();
+        assert_eq!(subtract.get_voxel(OctreePosition(12, 16, 16)), Voxel::EMPTY);
+        assert_eq!(subtract.get_voxel(OctreePosition(2, 2, 2)), Voxel::STONE);
+
+        let mut intersect = first.clone();
+        intersect.intersect(&second).unwrap();
+        assert_eq!(intersect.get_voxel(OctreePosition(12, 16, 16)), Voxel::STONE);
+        assert_eq!(intersect.get_voxel(OctreePosition(2, 2, 2)), Voxel::EMPTY);
+        assert_eq!(intersect.get_voxel(OctreePosition(20, 16, 16)), Voxel::EMPTY);
+
+        let mut empty = Octree::new(5, Some(Voxel::EMPTY));
+        empty.union(&first).unwrap();
+        empty.subtract(&first).unwrap();
+        assert_eq!(empty, Octree::new(5, Some(Voxel::EMPTY)));
+
+        assert!(first.union(&Octree::new(4, None)).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use std::mem::size_of;
+
+use bevy::utils::HashMap;
+
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DagNodeId(u32);
+
+/// Node of a sparse voxel DAG, the size of a node is given by its depth so identical subtrees
+/// of different sizes are shared as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DagNode {
+    Childs([DagNodeId; 8]),
+    Voxel(Voxel),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DagEntry {
+    node: DagNode,
+    ref_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DagStats {
+    /// Nodes stored once in the DAG
+    pub dag_nodes: u64,
+    /// Nodes the same octrees would have as `Octree`s
+    pub tree_nodes: u64,
+    pub dag_bytes: u64,
+    pub tree_bytes: u64,
+}
+
+impl DagStats {
+    pub fn saved_bytes(&self) -> i64 {
+        self.tree_bytes as i64 - self.dag_bytes as i64
+    }
+
+    pub fn compression_ratio(&self) -> f32 {
+        self.tree_bytes as f32 / self.dag_bytes.max(1) as f32
+    }
+}
+
+/// Hash-consed node storage shared by every `DagOctree` created with it, identical subtrees are stored once.
+/// Nodes are reference counted: a node is referenced by each parent node and by each octree whose root it is.
+#[derive(Debug, Default)]
+pub struct DagStore {
+    entries: Vec<DagEntry>,
+    free_entries: Vec<u32>,
+    lookup: HashMap<DagNode, DagNodeId>,
+}
+
+impl DagStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node(&self, id: DagNodeId) -> DagNode {
+        self.entries[id.0 as usize].node
+    }
+
+    pub fn ref_count(&self, id: DagNodeId) -> u32 {
+        self.entries[id.0 as usize].ref_count
+    }
+
+    /// Number of distinct live nodes
+    pub fn node_count(&self) -> usize {
+        self.lookup.len()
+    }
+
+    pub fn stats<'a>(&self, octrees: impl IntoIterator<Item = &'a DagOctree>) -> DagStats {
+        let mut expanded_counts = HashMap::new();
+        let tree_nodes = octrees.into_iter()
+            .map(|octree| self.expanded_count(octree.root, &mut expanded_counts))
+            .sum::<u64>();
+
+        let dag_nodes = self.node_count() as u64;
+
+        DagStats {
+            dag_nodes,
+            tree_nodes,
+            dag_bytes: dag_nodes * (size_of::<DagEntry>() + size_of::<DagNode>() + size_of::<DagNodeId>()) as u64,
+            tree_bytes: tree_nodes * size_of::<Octree>() as u64,
+        }
+    }
+
+    fn expanded_count(&self, id: DagNodeId, expanded_counts: &mut HashMap<DagNodeId, u64>) -> u64 {
+        if let Some(count) = expanded_counts.get(&id) {
+            return *count;
+        }
+
+        let count = match self.node(id) {
+            DagNode::Voxel(_) => 1,
+            DagNode::Childs(childs) => 1 + childs.iter().map(|child| self.expanded_count(*child, expanded_counts)).sum::<u64>(),
+        };
+        expanded_counts.insert(id, count);
+        count
+    }
+
+    /// Returns the id of node, the references to the childs of node are taken over by the returned node.
+    fn intern(&mut self, node: DagNode) -> DagNodeId {
+        if let Some(&id) = self.lookup.get(&node) {
+            // The existing node already references the same childs
+            if let DagNode::Childs(childs) = node {
+                for child in childs {
+                    self.release(child);
+                }
+            }
+            self.retain(id);
+            return id;
+        }
+
+        let entry = DagEntry { node, ref_count: 1 };
+        let id = match self.free_entries.pop() {
+            Some(index) => {
+                self.entries[index as usize] = entry;
+                DagNodeId(index)
+            }
+            None => {
+                self.entries.push(entry);
+                DagNodeId(self.entries.len() as u32 - 1)
+            }
+        };
+        self.lookup.insert(node, id);
+        id
+    }
+
+    fn retain(&mut self, id: DagNodeId) {
+        self.entries[id.0 as usize].ref_count += 1;
+    }
+
+    fn release(&mut self, id: DagNodeId) {
+        let entry = &mut self.entries[id.0 as usize];
+        entry.ref_count -= 1;
+
+        if entry.ref_count == 0 {
+            let node = entry.node;
+            self.lookup.remove(&node);
+            self.free_entries.push(id.0);
+
+            if let DagNode::Childs(childs) = node {
+                for child in childs {
+                    self.release(child);
+                }
+            }
+        }
+    }
+
+    fn intern_octree(&mut self, octree: &Octree) -> DagNodeId {
+        match octree.content {
+            OctreeContent::Voxel(voxel) => self.intern(DagNode::Voxel(voxel)),
+            OctreeContent::Childs(ref childs) => {
+                let childs = std::array::from_fn(|i| self.intern_octree(&childs[i]));
+                self.intern(DagNode::Childs(childs))
+            }
+        }
+    }
+
+    //Takes over the reference to id and returns a reference to the edited node
+    fn set_voxel_node(&mut self, id: DagNodeId, node_size: u8, pos: OctreePosition, size: u8, voxel: Voxel) -> DagNodeId {
+        if node_size == size {
+            self.release(id);
+            return self.intern(DagNode::Voxel(voxel));
+        }
+
+        let mut childs = match self.node(id) {
+            DagNode::Childs(childs) => childs,
+            DagNode::Voxel(current_voxel) if current_voxel == voxel => return id,
+            DagNode::Voxel(_) => [id; 8],
+        };
+        for child in childs {
+            self.retain(child);
+        }
+        self.release(id);
+
+        let indice = pos.get_child_indice(node_size - 1);
+        childs[indice] = self.set_voxel_node(childs[indice], node_size - 1, pos, size, voxel);
+
+        // Eight identical voxel childs are merged back like `Octree::set_voxel` does
+        if childs.iter().all(|child| *child == childs[0]) && self.node(childs[0]) == DagNode::Voxel(voxel) {
+            for child in &childs[1..] {
+                self.release(*child);
+            }
+            return childs[0];
+        }
+
+        self.intern(DagNode::Childs(childs))
+    }
+
+    fn to_octree_node(&self, id: DagNodeId, size: u8) -> Octree {
+        match self.node(id) {
+            DagNode::Voxel(voxel) => Octree::new(size, Some(voxel)),
+            DagNode::Childs(childs) => Octree::with_childs(size, std::array::from_fn(|i| Box::new(self.to_octree_node(childs[i], size - 1)))),
+        }
+    }
+}
+
+/// Octree whose nodes live in a `DagStore`. Copies share all their nodes and `set_voxel` only
+/// rebuilds the path to the edited node.
+/// An octree holds a reference to its root and must be given back with `DagOctree::release`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DagOctree {
+    pub size: u8,
+    root: DagNodeId,
+}
+
+impl DagOctree {
+    //Can panic if size>63
+    pub fn new(store: &mut DagStore, size: u8, voxel: Option<Voxel>) -> Self {
+        if size > 63 {
+            panic!("Max size is 63");
+        }
+        Self {
+            size,
+            root: store.intern(DagNode::Voxel(voxel.unwrap_or(Voxel::EMPTY))),
+        }
+    }
+
+    pub fn from_octree(store: &mut DagStore, octree: &Octree) -> Self {
+        Self {
+            size: octree.size,
+            root: store.intern_octree(octree),
+        }
+    }
+
+    pub fn to_octree(&self, store: &DagStore) -> Octree {
+        let octree = store.to_octree_node(self.root, self.size);
+        octree.debug_validate(false);
+        octree
+    }
+
+    pub fn root(&self) -> DagNodeId {
+        self.root
+    }
+
+    /// Shares every node with self, edits to one of the copies do not affect the other
+    pub fn copy(&self, store: &mut DagStore) -> Self {
+        store.retain(self.root);
+        Self {
+            size: self.size,
+            root: self.root,
+        }
+    }
+
+    pub fn release(self, store: &mut DagStore) {
+        store.release(self.root);
+    }
+
+    pub fn get_voxel(&self, store: &DagStore, pos: OctreePosition) -> Voxel {
+        let mut current_size = self.size;
+        let mut current_node = store.node(self.root);
+        loop {
+            match current_node {
+                DagNode::Voxel(voxel) => return voxel,
+                DagNode::Childs(childs) => {
+                    current_size -= 1;
+                    current_node = store.node(childs[pos.get_child_indice(current_size)]);
+                }
+            }
+        }
+    }
+
+    pub fn set_voxel(&mut self, store: &mut DagStore, pos: OctreePosition, size: u8, voxel: Voxel) -> Result<(), OctreeError> {
+        if size > self.size {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        self.root = store.set_voxel_node(self.root, self.size, pos, size, voxel);
+        Ok(())
+    }
+
+    pub fn voxel_iterator<'a>(&self, store: &'a DagStore) -> DagOctreeIterator<'a> {
+        DagOctreeIterator::new(store, self)
+    }
+}
+
+/// Iterates over the leaves of a `DagOctree` in Morton order, like `OctreeIterator`.
+pub struct DagOctreeIterator<'a> {
+    stack: Vec<(DagNodeId, u8, OctreePosition)>,
+    store: &'a DagStore,
+}
+
+impl<'a> DagOctreeIterator<'a> {
+    pub fn new(store: &'a DagStore, octree: &DagOctree) -> Self {
+        let mut stack = Vec::with_capacity(octree.size as usize * 7 + 1);
+        stack.push((octree.root, octree.size, OctreePosition(0, 0, 0)));
+
+        DagOctreeIterator {
+            stack,
+            store,
+        }
+    }
+}
+
+impl<'a> Iterator for DagOctreeIterator<'a> {
+    type Item = (Voxel, OctreePosition, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (id, size, pos) = self.stack.pop()?;
+
+            match self.store.node(id) {
+                DagNode::Voxel(voxel) => return Some((voxel, pos, size)),
+                DagNode::Childs(childs) => {
+                    for indice in (0..8).rev() {
+                        self.stack.push((childs[indice], size - 1, pos.child(indice, size - 1)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::block_on;
+
+    use super::*;
+
+    fn generated_octree(offset: i128) -> Octree {
+        let heigh_map: Vec<Vec<i128>> = (0..16).map(|i| (0..16).map(|j| (i + j) / 4 + offset).collect()).collect();
+        let mut tree = Octree::new(6, None);
+        block_on(tree.fill_with_heigh_map(heigh_map, 2, Voxel::STONE));
+        tree
+    }
+
+    #[test]
+    fn dag_conversion_is_lossless() {
+        let mut store = DagStore::new();
+        let tree = generated_octree(0);
+        let dag = DagOctree::from_octree(&mut store, &tree);
+
+        assert_eq!(dag.to_octree(&store), tree);
+        assert!(dag.voxel_iterator(&store).eq(tree.voxel_iterator()));
+
+        let stats = store.stats([&dag]);
+        assert!(stats.dag_nodes < stats.tree_nodes);
+    }
+
+    #[test]
+    fn dag_shares_subtrees_across_octrees() {
+        let mut store = DagStore::new();
+        let first = DagOctree::from_octree(&mut store, &generated_octree(0));
+        let node_count = store.node_count();
+
+        let second = DagOctree::from_octree(&mut store, &generated_octree(0));
+        assert_eq!(store.node_count(), node_count);
+        assert_eq!(first.root(), second.root());
+
+        let third = DagOctree::from_octree(&mut store, &generated_octree(1));
+        assert!(store.node_count() < node_count * 2);
+
+        first.release(&mut store);
+        second.release(&mut store);
+        third.release(&mut store);
+        assert_eq!(store.node_count(), 0);
+    }
+
+    #[test]
+    fn dag_set_voxel_copy_on_write() {
+        let mut store = DagStore::new();
+        let tree = generated_octree(0);
+        let original = DagOctree::from_octree(&mut store, &tree);
+        let mut edited = original.copy(&mut store);
+
+        edited.set_voxel(&mut store, OctreePosition(2, 60, 1), 0, Voxel::DIRT).unwrap();
+
+        let mut edited_tree = tree.clone();
+        edited_tree.set_voxel(OctreePosition(2, 60, 1), 0, Voxel::DIRT).unwrap();
+
+        assert_eq!(original.to_octree(&store), tree);
+        assert_eq!(edited.to_octree(&store), edited_tree);
+        assert_eq!(edited.get_voxel(&store, OctreePosition(2, 60, 1)), Voxel::DIRT);
+
+        edited.release(&mut store);
+        original.release(&mut store);
+        assert_eq!(store.node_count(), 0);
+    }
+}
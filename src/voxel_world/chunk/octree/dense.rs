@@ -0,0 +1,101 @@
+use std::array::from_fn;
+
+use super::{Octree, OctreeError, OctreePosition};
+
+/// Index of (x, y, z) in a dense grid of the given dimensions, x varies the fastest then y then z,
+/// like the child indices of `OctreePosition::get_child_indice`.
+pub fn dense_index(dimensions: [usize; 3], x: usize, y: usize, z: usize) -> usize {
+    x + dimensions[0] * (y + dimensions[1] * z)
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Builds the maximally merged octree of a dense grid laid out as in `dense_index`.
+    /// The octree is the smallest one containing the grid, the voxels outside of the grid are set to padding.
+    pub fn from_dense(voxels: &[T], dimensions: [usize; 3], padding: T) -> Result<Self, OctreeError> {
+        if voxels.len() != dimensions.iter().product::<usize>() {
+            return Err(OctreeError::SizeMismatch);
+        }
+
+        let largest_side = dimensions.into_iter().max().unwrap_or(0).max(1) as u64;
+        let size = (u64::BITS - (largest_side - 1).leading_zeros()) as u8;
+        if size > 63 {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        let octree = Self::from_dense_node(voxels, dimensions, &padding, OctreePosition(0, 0, 0), size);
+        octree.debug_validate(true);
+        Ok(octree)
+    }
+
+    fn from_dense_node(voxels: &[T], dimensions: [usize; 3], padding: &T, pos: OctreePosition, size: u8) -> Self {
+        let (x, y, z) = (pos.0 as usize, pos.1 as usize, pos.2 as usize);
+
+        // Whole node in the padding
+        if x >= dimensions[0] || y >= dimensions[1] || z >= dimensions[2] {
+            return Octree::filled(size, padding.clone());
+        }
+        if size == 0 {
+            return Octree::filled(0, voxels[dense_index(dimensions, x, y, z)].clone());
+        }
+
+        let mut node = Octree::with_childs(size, from_fn(|indice| {
+            Box::new(Self::from_dense_node(voxels, dimensions, padding, pos.child(indice, size - 1), size - 1))
+        }));
+        node.merge_childs();
+        node
+    }
+
+    /// Dense grid of the given dimensions laid out as in `dense_index`, starting at the origin of the octree.
+    /// Use `[self.cart_size() as usize; 3]` to export the whole octree.
+    pub fn to_dense(&self, dimensions: [usize; 3]) -> Result<Vec<T>, OctreeError> {
+        if dimensions.iter().any(|side| *side as u64 > self.cart_size()) {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        let mut voxels = Vec::with_capacity(dimensions.iter().product());
+        for z in 0..dimensions[2] {
+            for y in 0..dimensions[1] {
+                for x in 0..dimensions[0] {
+                    voxels.push(self.get_voxel(OctreePosition(x as u64, y as u64, z as u64)));
+                }
+            }
+        }
+        Ok(voxels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{OctreeContent, Voxel};
+    use super::*;
+
+    #[test]
+    fn dense_round_trip() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(8, 0, 8), 3, Voxel::DIRT).unwrap();
+
+        let voxels = tree.to_dense([16; 3]).unwrap();
+        assert_eq!(voxels[dense_index([16; 3], 2, 5, 1)], Voxel::STONE);
+        assert_eq!(voxels.iter().filter(|voxel| **voxel == Voxel::DIRT).count(), 512);
+        assert_eq!(Octree::from_dense(&voxels, [16; 3], Voxel::EMPTY).unwrap(), tree);
+    }
+
+    #[test]
+    fn dense_import_is_merged_and_padded() {
+        let dimensions = [5, 3, 2];
+        let voxels: Vec<u8> = (0..30).map(|i| if i % 5 == 4 { 1 } else { 0 }).collect();
+
+        let tree = Octree::from_dense(&voxels, dimensions, 0).unwrap();
+        assert_eq!(tree.size, 3);
+        assert_eq!(tree.to_dense(dimensions).unwrap(), voxels);
+        // The x < 4, y < 4, z < 4 node only holds zeros and padding, it is merged into a single leaf
+        assert_eq!(tree.get_cube(OctreePosition(0, 0, 0), 1).unwrap().content, OctreeContent::Voxel(0));
+        assert_eq!(tree.get_voxel(OctreePosition(4, 2, 1)), 1);
+        assert_eq!(tree.get_voxel(OctreePosition(4, 3, 1)), 0);
+        assert_eq!(tree.get_cube(OctreePosition(0, 0, 4), 0).unwrap().size, 2);
+
+        assert!(matches!(Octree::from_dense(&voxels, [5, 3, 3], 0), Err(OctreeError::SizeMismatch)));
+        assert!(matches!(tree.to_dense([9, 1, 1]), Err(OctreeError::SizeLargerThanOctree)));
+    }
+}
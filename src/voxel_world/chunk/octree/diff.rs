@@ -0,0 +1,125 @@
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+/// Node replacements turning an octree into another one, see `Octree::diff`.
+/// The replaced content is kept so the patch can be inverted for undo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OctreePatch<T = Voxel> {
+    /// Size of the octrees the patch applies to
+    pub octree_size: u8,
+    /// Nodes set by the patch as (position, size, voxel), they never overlap
+    pub changes: Vec<(OctreePosition, u8, T)>,
+    /// Nodes set by the inverse patch, what changes replaced
+    pub reverts: Vec<(OctreePosition, u8, T)>,
+}
+
+impl<T: Clone + Eq> OctreePatch<T> {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Patch undoing self
+    pub fn inverse(&self) -> Self {
+        Self {
+            octree_size: self.octree_size,
+            changes: self.reverts.clone(),
+            reverts: self.changes.clone(),
+        }
+    }
+
+    pub fn apply(&self, octree: &mut Octree<T>) -> Result<(), OctreeError> {
+        if octree.size != self.octree_size {
+            return Err(OctreeError::SizeMismatch);
+        }
+
+        for (pos, size, voxel) in self.changes.iter() {
+            octree.set_voxel(*pos, *size, voxel.clone())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Smallest set of node replacements turning self into other.
+    /// A leaf replaced by a subtree only gives the leaves of the subtree that differ from it, and the other way around.
+    pub fn diff(&self, other: &Octree<T>) -> Result<OctreePatch<T>, OctreeError> {
+        if self.size != other.size {
+            return Err(OctreeError::SizeMismatch);
+        }
+
+        let mut patch = OctreePatch {
+            octree_size: self.size,
+            changes: Vec::new(),
+            reverts: Vec::new(),
+        };
+        self.diff_node(other, OctreePosition(0, 0, 0), &mut patch);
+        Ok(patch)
+    }
+
+    fn diff_node(&self, other: &Octree<T>, pos: OctreePosition, patch: &mut OctreePatch<T>) {
+        match (&self.content, &other.content) {
+            (OctreeContent::Voxel(voxel), OctreeContent::Voxel(other_voxel)) => {
+                if voxel != other_voxel {
+                    patch.changes.push((pos, self.size, other_voxel.clone()));
+                    patch.reverts.push((pos, self.size, voxel.clone()));
+                }
+            }
+            (OctreeContent::Voxel(voxel), OctreeContent::Childs(_)) => {
+                patch.reverts.push((pos, self.size, voxel.clone()));
+                other.push_differing_leaves(pos, voxel, &mut patch.changes);
+            }
+            (OctreeContent::Childs(_), OctreeContent::Voxel(other_voxel)) => {
+                patch.changes.push((pos, self.size, other_voxel.clone()));
+                self.push_differing_leaves(pos, other_voxel, &mut patch.reverts);
+            }
+            (OctreeContent::Childs(childs), OctreeContent::Childs(other_childs)) => {
+                for (indice, (child, other_child)) in childs.iter().zip(other_childs.iter()).enumerate() {
+                    child.diff_node(other_child, pos.child(indice, child.size), patch);
+                }
+            }
+        }
+    }
+
+    fn push_differing_leaves(&self, pos: OctreePosition, voxel: &T, leaves: &mut Vec<(OctreePosition, u8, T)>) {
+        for (leaf_voxel, leaf_pos, leaf_size) in self.voxel_iterator() {
+            if leaf_voxel != *voxel {
+                let leaf_pos = OctreePosition(pos.0 | leaf_pos.0, pos.1 | leaf_pos.1, pos.2 | leaf_pos.2);
+                leaves.push((leaf_pos, leaf_size, leaf_voxel));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::*;
+
+    #[test]
+    fn diff_and_patch() {
+        let mut before = Octree::new(5, Some(Voxel::EMPTY));
+        before.fill_box(DVec3::ZERO, DVec3::new(32., 8., 32.), Voxel::STONE);
+        before.set_voxel(OctreePosition(20, 20, 20), 0, Voxel::DIRT).unwrap();
+
+        let mut after = before.clone();
+        after.fill_sphere(DVec3::new(16., 8., 16.), 5., Voxel::EMPTY);
+        after.set_voxel(OctreePosition(16, 16, 16), 4, Voxel::EMPTY).unwrap();
+        after.set_voxel(OctreePosition(1, 30, 1), 0, Voxel::DIRT).unwrap();
+
+        let patch = before.diff(&after).unwrap();
+        assert!(before.diff(&before).unwrap().is_empty());
+        // The cleared node is a single change, its former dirt voxel is a single revert
+        assert!(patch.changes.contains(&(OctreePosition(16, 16, 16), 4, Voxel::EMPTY)));
+        assert!(patch.reverts.contains(&(OctreePosition(20, 20, 20), 0, Voxel::DIRT)));
+
+        let mut patched = before.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, after);
+
+        patch.inverse().apply(&mut patched).unwrap();
+        assert_eq!(patched, before);
+        assert_eq!(patch.inverse().inverse(), patch);
+
+        assert!(matches!(patch.apply(&mut Octree::new(4, None)), Err(OctreeError::SizeMismatch)));
+    }
+}
@@ -0,0 +1,100 @@
+use super::{Octree, OctreeContent, OctreePosition};
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Copy of the octree where every node of size levels is collapsed into one leaf, holding the payload
+    /// covering the largest volume of the node. Ties go to the payload found first in Morton order.
+    pub fn downsample(&self, levels: u8) -> Octree<T> {
+        self.downsample_node(levels.min(self.size), &|node: &Octree<T>| node.majority_voxel(|_, _| true))
+    }
+
+    /// Like `downsample`, but a node is solid as soon as a solid leaf touches one of its faces,
+    /// so thin walls and floors do not vanish from the coarser octree.
+    pub fn downsample_preserving_surface(&self, levels: u8, is_solid: impl Fn(&T) -> bool) -> Octree<T> {
+        self.downsample_node(levels.min(self.size), &|node: &Octree<T>| {
+            let limit = node.cart_size();
+            let on_face = |voxel: &T, pos: OctreePosition, size: u8| {
+                let end = Octree::octree_size_to_cartestian(size);
+                is_solid(voxel) && [pos.0, pos.1, pos.2].iter().any(|coord| *coord == 0 || coord + end == limit)
+            };
+
+            node.majority_voxel(|voxel, (pos, size)| on_face(voxel, pos, size))
+                .or_else(|| node.majority_voxel(|_, _| true))
+        })
+    }
+
+    fn downsample_node(&self, levels: u8, collapse: &impl Fn(&Octree<T>) -> Option<T>) -> Octree<T> {
+        match self.content {
+            OctreeContent::Voxel(_) => self.clone(),
+            OctreeContent::Childs(_) if self.size <= levels => Octree::filled(self.size, collapse(self).unwrap()),
+            OctreeContent::Childs(ref childs) => {
+                let mut node = Octree::with_childs(self.size, std::array::from_fn(|indice| Box::new(childs[indice].downsample_node(levels, collapse))));
+                node.merge_childs();
+                node
+            }
+        }
+    }
+
+    // Payload of the largest volume among the leaves accepted by filter, positions being relative to self
+    fn majority_voxel(&self, filter: impl Fn(&T, (OctreePosition, u8)) -> bool) -> Option<T> {
+        let mut volumes: Vec<(T, u128)> = Vec::new();
+        for (voxel, pos, size) in self.voxel_iterator() {
+            if !filter(&voxel, (pos, size)) {
+                continue;
+            }
+            let volume = 1u128.checked_shl(3 * size as u32).unwrap_or(u128::MAX);
+            match volumes.iter_mut().find(|(other, _)| *other == voxel) {
+                Some((_, total)) => *total = total.saturating_add(volume),
+                None => volumes.push((voxel, volume)),
+            }
+        }
+
+        // max_by_key keeps the last maximum, iterate backwards to keep the first one
+        volumes.into_iter().rev().max_by_key(|(_, volume)| *volume).map(|(voxel, _)| voxel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::super::Voxel;
+    use super::*;
+
+    #[test]
+    fn majority_downsampling() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        // 5 of the 8 voxels of the node at (0, 0, 0) of size 1 are stone
+        tree.fill_box(DVec3::ZERO, DVec3::new(2., 1., 2.), Voxel::STONE);
+        tree.set_voxel(OctreePosition(0, 1, 0), 0, Voxel::STONE).unwrap();
+        // 4 dirt and 4 stone voxels, the first one in Morton order wins
+        tree.fill_box(DVec3::splat(8.), DVec3::new(10., 10., 9.), Voxel::DIRT);
+        tree.fill_box(DVec3::new(8., 8., 9.), DVec3::splat(10.), Voxel::STONE);
+
+        let downsampled = tree.downsample(1);
+        assert_eq!(downsampled.get_cube(OctreePosition(0, 0, 0), 0).unwrap().size, 1);
+        assert_eq!(downsampled.get_voxel(OctreePosition(1, 1, 1)), Voxel::STONE);
+        assert_eq!(downsampled.get_voxel(OctreePosition(9, 9, 9)), Voxel::DIRT);
+        assert_eq!(downsampled.validate(), Ok(()));
+
+        assert_eq!(tree.downsample(0), tree);
+        assert_eq!(tree.downsample(2).get_voxel(OctreePosition(8, 8, 8)), Voxel::EMPTY);
+        assert_eq!(tree.downsample(10), Octree::new(4, Some(Voxel::EMPTY)));
+        assert!(tree.downsample(2).voxel_iterator().all(|(_, _, size)| size >= 2));
+    }
+
+    #[test]
+    fn surface_preserving_downsampling() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        // Thin floor at the bottom of the node at (0, 0, 0) of size 2
+        tree.fill_box(DVec3::ZERO, DVec3::new(4., 1., 4.), Voxel::STONE);
+        // Voxel in the middle of the node at (8, 8, 8), not touching its faces
+        tree.set_voxel(OctreePosition(9, 9, 9), 0, Voxel::DIRT).unwrap();
+
+        let is_solid = |voxel: &Voxel| *voxel != Voxel::EMPTY;
+        let downsampled = tree.downsample_preserving_surface(2, is_solid);
+        assert_eq!(downsampled.get_voxel(OctreePosition(0, 3, 0)), Voxel::STONE);
+        assert_eq!(downsampled.get_voxel(OctreePosition(8, 8, 8)), Voxel::EMPTY);
+
+        assert_eq!(tree.downsample(2).get_voxel(OctreePosition(0, 3, 0)), Voxel::EMPTY);
+    }
+}
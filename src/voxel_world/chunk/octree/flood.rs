@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+
+use bevy::math::I64Vec3;
+use bevy::utils::{HashMap, HashSet};
+
+use super::neighbour::NeighbourDirection;
+use super::{Octree, OctreeContent, OctreePosition};
+
+/// Leaves of an octree connected through their faces, found by `Octree::connected_region`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectedRegion {
+    /// Leaves of the region as (position, size)
+    pub leaves: Vec<(OctreePosition, u8)>,
+    /// Faces of leaves of the region on the border of the octree, the region may continue in the adjacent chunk.
+    /// Empty for a region enclosed in its octree, like a floating island.
+    pub border_faces: Vec<(OctreePosition, u8, NeighbourDirection)>,
+}
+
+impl ConnectedRegion {
+    /// Volume in voxels of size 0
+    pub fn volume(&self) -> u128 {
+        self.leaves.iter().map(|(_, size)| 1u128 << (3 * *size as u32)).sum()
+    }
+}
+
+/// Connected region spanning several octrees of the same size laid out on a grid, like the chunks of the world.
+/// octrees gives the octree of a grid position, the region stops at the missing ones and lists the faces touching them
+/// in `ConnectedRegion::border_faces`. Returns the part of the region in each octree it reaches.
+pub fn connected_region_across<'a, T: Clone + Eq + 'a>(
+    octrees: impl Fn(I64Vec3) -> Option<&'a Octree<T>>,
+    start_chunk: I64Vec3,
+    start: OctreePosition,
+    connects: impl Fn(&T) -> bool,
+) -> HashMap<I64Vec3, ConnectedRegion> {
+    let mut regions: HashMap<I64Vec3, ConnectedRegion> = HashMap::new();
+    let Some(start_octree) = octrees(start_chunk) else {
+        return regions;
+    };
+
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    let start_leaf = start_octree.get_cube(start, 0).unwrap();
+    if let OctreeContent::Voxel(ref voxel) = start_leaf.content {
+        if connects(voxel) {
+            let start = start.parent(start_leaf.size);
+            visited.insert((start_chunk, start));
+            queue.push_back((start_chunk, start, start_leaf.size));
+        }
+    }
+
+    while let Some((chunk, pos, size)) = queue.pop_front() {
+        let octree = octrees(chunk).unwrap();
+        let limit = octree.cart_size() as i128;
+        let side = Octree::octree_size_to_cartestian(size) as i128;
+        regions.entry(chunk).or_default().leaves.push((pos, size));
+
+        for direction in NeighbourDirection::FACES {
+            // Position of the neighbouring node of the same size, possibly in the adjacent octree
+            let raw = [
+                pos.0 as i128 + direction.0 as i128 * side,
+                pos.1 as i128 + direction.1 as i128 * side,
+                pos.2 as i128 + direction.2 as i128 * side,
+            ];
+            let chunk_offset = raw.map(|coord| coord.div_euclid(limit) as i64);
+            let neighbour_chunk = chunk + I64Vec3::from_array(chunk_offset);
+            let neighbour_pos = raw.map(|coord| coord.rem_euclid(limit) as u64);
+
+            let neighbour_octree = match octrees(neighbour_chunk) {
+                Some(neighbour_octree) if neighbour_octree.size == octree.size => neighbour_octree,
+                _ => {
+                    regions.entry(chunk).or_default().border_faces.push((pos, size, direction));
+                    continue;
+                }
+            };
+
+            let neighbour_pos = OctreePosition(neighbour_pos[0], neighbour_pos[1], neighbour_pos[2]);
+            for (voxel, leaf_pos, leaf_size) in neighbour_octree.neighbour_at(neighbour_pos, size, direction).leaves() {
+                if connects(&voxel) && visited.insert((neighbour_chunk, leaf_pos)) {
+                    queue.push_back((neighbour_chunk, leaf_pos, leaf_size));
+                }
+            }
+        }
+    }
+
+    regions
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Leaves connected to the leaf containing start through faces of leaves whose payload connects.
+    /// The search goes from leaf to leaf, a large empty node is a single step. Empty if the start leaf does not connect.
+    pub fn connected_region(&self, start: OctreePosition, connects: impl Fn(&T) -> bool) -> ConnectedRegion {
+        let mut regions = connected_region_across(|chunk| (chunk == I64Vec3::ZERO).then_some(self), I64Vec3::ZERO, start, connects);
+        regions.remove(&I64Vec3::ZERO).unwrap_or_default()
+    }
+
+    /// Sets the connected region of start to voxel, see `Octree::connected_region`
+    pub fn flood_fill(&mut self, start: OctreePosition, connects: impl Fn(&T) -> bool, voxel: T) -> ConnectedRegion {
+        let region = self.connected_region(start, connects);
+        self.fill_region(&region, voxel);
+        region
+    }
+
+    /// Sets every leaf of region to voxel, e.g. with the regions of `connected_region_across`
+    pub fn fill_region(&mut self, region: &ConnectedRegion, voxel: T) {
+        for (pos, size) in region.leaves.iter() {
+            self.set_voxel(*pos, *size, voxel.clone()).unwrap();
+        }
+    }
+
+    /// Labels the connected components of the leaves matching is_solid, in Morton order of their first leaf
+    pub fn connected_components(&self, is_solid: impl Fn(&T) -> bool) -> Vec<ConnectedRegion> {
+        let mut labelled = HashSet::new();
+        let mut components = Vec::new();
+
+        for (voxel, pos, _) in self.voxel_iterator() {
+            if is_solid(&voxel) && !labelled.contains(&pos) {
+                let component = self.connected_region(pos, &is_solid);
+                labelled.extend(component.leaves.iter().map(|(pos, _)| *pos));
+                components.push(component);
+            }
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::super::Voxel;
+    use super::*;
+
+    fn is_empty(voxel: &Voxel) -> bool {
+        *voxel == Voxel::EMPTY
+    }
+
+    #[test]
+    fn flood_fill_enclosed_room() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        // Hollow stone box, its inside is cut from the rest of the empty space
+        tree.fill_box(DVec3::splat(4.), DVec3::splat(12.), Voxel::STONE);
+        tree.fill_box(DVec3::splat(5.), DVec3::splat(11.), Voxel::EMPTY);
+
+        let outside = tree.connected_region(OctreePosition(0, 0, 0), is_empty);
+        assert_eq!(outside.volume(), 32 * 32 * 32 - 8 * 8 * 8);
+        // Large empty nodes are single leaves of the region
+        assert!(outside.leaves.len() < 1000);
+        assert!(!outside.border_faces.is_empty());
+
+        let room = tree.flood_fill(OctreePosition(7, 7, 7), is_empty, Voxel::DIRT);
+        assert_eq!(room.volume(), 6 * 6 * 6);
+        assert!(room.border_faces.is_empty());
+        assert_eq!(tree.get_voxel(OctreePosition(10, 10, 10)), Voxel::DIRT);
+        assert_eq!(tree.get_voxel(OctreePosition(20, 20, 20)), Voxel::EMPTY);
+
+        assert_eq!(tree.connected_region(OctreePosition(4, 4, 4), is_empty), ConnectedRegion::default());
+    }
+
+    #[test]
+    fn label_floating_islands() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.fill_box(DVec3::ZERO, DVec3::new(32., 4., 32.), Voxel::STONE);
+        tree.fill_box(DVec3::new(10., 10., 10.), DVec3::new(13., 12., 14.), Voxel::DIRT);
+        tree.set_voxel(OctreePosition(20, 20, 20), 0, Voxel::STONE).unwrap();
+        // Only touching each other through an edge, faces are not shared
+        tree.set_voxel(OctreePosition(3, 6, 3), 0, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(4, 7, 3), 0, Voxel::DIRT).unwrap();
+
+        let components = tree.connected_components(|voxel| !is_empty(voxel));
+        assert_eq!(components.len(), 5);
+
+        let islands: Vec<_> = components.iter().filter(|component| component.border_faces.is_empty()).collect();
+        assert_eq!(islands.len(), 4);
+        assert_eq!(islands.iter().map(|island| island.volume()).sum::<u128>(), 3 * 2 * 4 + 3);
+    }
+
+    #[test]
+    fn flood_fill_across_chunks() {
+        let mut first = Octree::new(3, Some(Voxel::STONE));
+        let mut second = Octree::new(3, Some(Voxel::STONE));
+        // Tunnel along x going through both chunks, the second one is closed at its end
+        first.fill_box(DVec3::new(0., 2., 2.), DVec3::new(8., 3., 3.), Voxel::EMPTY);
+        second.fill_box(DVec3::new(0., 2., 2.), DVec3::new(5., 3., 3.), Voxel::EMPTY);
+
+        let octrees = |chunk: I64Vec3| match chunk {
+            I64Vec3 { x: 0, y: 0, z: 0 } => Some(&first),
+            I64Vec3 { x: 1, y: 0, z: 0 } => Some(&second),
+            _ => None,
+        };
+        let regions = connected_region_across(octrees, I64Vec3::ZERO, OctreePosition(3, 2, 2), is_empty);
+
+        assert_eq!(regions[&I64Vec3::ZERO].volume(), 8);
+        assert_eq!(regions[&I64Vec3::X].volume(), 5);
+        // The tunnel opens at x = 0 of the first chunk, whose neighbour is not loaded
+        assert_eq!(regions[&I64Vec3::ZERO].border_faces, vec![(OctreePosition(0, 2, 2), 0, NeighbourDirection::NEG_X)]);
+        assert!(regions[&I64Vec3::X].border_faces.is_empty());
+
+        second.fill_region(&regions[&I64Vec3::X], Voxel::DIRT);
+        assert_eq!(second.get_voxel(OctreePosition(4, 2, 2)), Voxel::DIRT);
+    }
+}
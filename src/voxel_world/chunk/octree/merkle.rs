@@ -0,0 +1,204 @@
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{Octree, OctreeContent, OctreePosition};
+
+// Marks a cache holding no hash, computed hashes of 0 are stored as 1
+const NO_HASH: u64 = 0;
+
+/// Merkle hash of a node, computed on demand and cleared by the methods editing the node.
+/// Caches are ignored when comparing octrees.
+#[derive(Debug, Default)]
+pub struct MerkleCache(AtomicU64);
+
+impl MerkleCache {
+    fn get(&self) -> Option<u64> {
+        match self.0.load(Ordering::Relaxed) {
+            NO_HASH => None,
+            hash => Some(hash),
+        }
+    }
+
+    fn set(&self, hash: u64) -> u64 {
+        let hash = hash.max(1);
+        self.0.store(hash, Ordering::Relaxed);
+        hash
+    }
+
+    pub(super) fn clear(&self) {
+        self.0.store(NO_HASH, Ordering::Relaxed);
+    }
+}
+
+impl Clone for MerkleCache {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+// Always equal so that the derived `PartialEq` of `Octree` compares the nodes only,
+// equal octrees hold different caches as soon as only one of them has been hashed
+impl PartialEq for MerkleCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for MerkleCache {}
+
+/// FNV-1a, unlike the std hashers its output is specified and stays the same across builds.
+/// Integers are hashed in little endian so the hashes do not depend on the platform either.
+pub struct MerkleHasher(u64);
+
+impl Default for MerkleHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for MerkleHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+impl<T: Clone + Eq + Hash> Octree<T> {
+    /// Structural hash of the node: equal for equal subtrees, so a node split into eight identical leaves
+    /// does not hash like the leaf. Cached in the node until it is edited.
+    pub fn merkle_hash(&self) -> u64 {
+        if let Some(hash) = self.hash_cache.get() {
+            return hash;
+        }
+
+        let mut hasher = MerkleHasher::default();
+        self.size.hash(&mut hasher);
+        match self.content {
+            OctreeContent::Voxel(ref voxel) => {
+                0u8.hash(&mut hasher);
+                voxel.hash(&mut hasher);
+            }
+            OctreeContent::Childs(ref childs) => {
+                1u8.hash(&mut hasher);
+                for child in childs.iter() {
+                    child.merkle_hash().hash(&mut hasher);
+                }
+            }
+        }
+        self.hash_cache.set(hasher.finish())
+    }
+
+    /// Largest nodes whose hashes differ between the two octrees, the subtrees with equal hashes are skipped
+    pub fn differing_subtrees(&self, other: &Octree<T>) -> Vec<(OctreePosition, u8)> {
+        let mut differing = Vec::new();
+        self.push_differing_subtrees(other, OctreePosition(0, 0, 0), &mut differing);
+        differing
+    }
+
+    fn push_differing_subtrees(&self, other: &Octree<T>, pos: OctreePosition, differing: &mut Vec<(OctreePosition, u8)>) {
+        if self.size == other.size && self.merkle_hash() == other.merkle_hash() {
+            return;
+        }
+
+        match (&self.content, &other.content) {
+            (OctreeContent::Childs(childs), OctreeContent::Childs(other_childs)) if self.size == other.size => {
+                for (indice, (child, other_child)) in childs.iter().zip(other_childs.iter()).enumerate() {
+                    child.push_differing_subtrees(other_child, pos.child(indice, child.size), differing);
+                }
+            }
+            _ => differing.push((pos, self.size)),
+        }
+    }
+}
+
+impl<T> Octree<T> {
+    /// Clears the cached hash of the node and of all its descendants
+    pub fn invalidate_hashes(&mut self) {
+        self.hash_cache.clear();
+        if let OctreeContent::Childs(ref mut childs) = self.content {
+            for child in childs.iter_mut() {
+                child.invalidate_hashes();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::super::Voxel;
+    use super::*;
+
+    #[test]
+    fn merkle_hash_is_cached_and_invalidated() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        let hash = tree.merkle_hash();
+        assert_eq!(tree.hash_cache.get(), Some(hash));
+
+        let mut edited = tree.clone();
+        edited.set_voxel(OctreePosition(30, 30, 30), 0, Voxel::DIRT).unwrap();
+        assert_ne!(edited.merkle_hash(), hash);
+        edited.set_voxel(OctreePosition(30, 30, 30), 0, Voxel::EMPTY).unwrap();
+        assert_eq!(edited.merkle_hash(), hash);
+
+        edited.get_cube_mut(OctreePosition(16, 16, 16), 4).unwrap().split().unwrap();
+        assert_ne!(edited.merkle_hash(), hash);
+        assert_eq!(Octree::new(5, Some(Voxel::EMPTY)).merkle_hash(), Octree::new(5, Some(Voxel::EMPTY)).merkle_hash());
+        assert_ne!(Octree::new(5, Some(Voxel::EMPTY)).merkle_hash(), Octree::new(4, Some(Voxel::EMPTY)).merkle_hash());
+    }
+
+    #[test]
+    fn fill_shape_keeps_hashes_of_untouched_nodes() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(20, 20, 20), 0, Voxel::STONE).unwrap();
+        tree.merkle_hash();
+
+        tree.fill_box(DVec3::new(20., 20., 20.), DVec3::new(22., 21., 21.), Voxel::DIRT);
+        assert_eq!(tree.hash_cache.get(), None);
+        assert!(tree.get_cube(OctreePosition(2, 5, 1), 0).unwrap().hash_cache.get().is_some());
+        assert!(tree.get_cube(OctreePosition(16, 16, 16), 4).unwrap().hash_cache.get().is_none());
+        assert!(tree.get_cube(OctreePosition(24, 16, 16), 3).unwrap().hash_cache.get().is_some());
+    }
+
+    #[test]
+    fn find_differing_subtrees() {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(20, 20, 20), 1, Voxel::DIRT).unwrap();
+
+        let mut other = tree.clone();
+        other.set_voxel(OctreePosition(20, 20, 20), 1, Voxel::STONE).unwrap();
+        other.set_voxel(OctreePosition(0, 16, 0), 4, Voxel::DIRT).unwrap();
+
+        assert!(tree.differing_subtrees(&tree.clone()).is_empty());
+        assert_eq!(
+            tree.differing_subtrees(&other),
+            vec![(OctreePosition(0, 16, 0), 4), (OctreePosition(20, 20, 20), 1)]
+        );
+    }
+}
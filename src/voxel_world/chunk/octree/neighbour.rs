@@ -0,0 +1,186 @@
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+/// Direction from a node to one of its 26 neighbours, each component is -1, 0 or 1.
+/// One non zero component is a face neighbour, two an edge neighbour and three a corner neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NeighbourDirection(pub i8, pub i8, pub i8);
+
+impl NeighbourDirection {
+    pub const POS_X: NeighbourDirection = NeighbourDirection(1, 0, 0);
+    pub const NEG_X: NeighbourDirection = NeighbourDirection(-1, 0, 0);
+    pub const POS_Y: NeighbourDirection = NeighbourDirection(0, 1, 0);
+    pub const NEG_Y: NeighbourDirection = NeighbourDirection(0, -1, 0);
+    pub const POS_Z: NeighbourDirection = NeighbourDirection(0, 0, 1);
+    pub const NEG_Z: NeighbourDirection = NeighbourDirection(0, 0, -1);
+
+    pub const FACES: [NeighbourDirection; 6] = [
+        Self::POS_X, Self::NEG_X, Self::POS_Y, Self::NEG_Y, Self::POS_Z, Self::NEG_Z,
+    ];
+
+    /// The 6 face, 12 edge and 8 corner directions
+    pub fn all() -> impl Iterator<Item = NeighbourDirection> {
+        (0..27)
+            .map(|i| NeighbourDirection(i % 3 - 1, (i / 3) % 3 - 1, i / 9 - 1))
+            .filter(|direction| direction.axis_count() != 0)
+    }
+
+    /// Number of non zero components, 1 for a face, 2 for an edge and 3 for a corner
+    pub fn axis_count(&self) -> u8 {
+        [self.0, self.1, self.2].iter().filter(|component| **component != 0).count() as u8
+    }
+
+    fn components(&self) -> [i8; 3] {
+        [self.0, self.1, self.2]
+    }
+}
+
+/// Neighbour of a node found by `Octree::neighbour`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighbour<T = Voxel> {
+    /// Leaf of the same size as the queried node or larger, with its position and size
+    Leaf(T, OctreePosition, u8),
+    /// The neighbour of the same size is split, these are its leaves touching the queried node
+    Leaves(Vec<(T, OctreePosition, u8)>),
+}
+
+impl<T> Neighbour<T> {
+    pub fn leaves(self) -> Vec<(T, OctreePosition, u8)> {
+        match self {
+            Neighbour::Leaf(voxel, pos, size) => vec![(voxel, pos, size)],
+            Neighbour::Leaves(leaves) => leaves,
+        }
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Neighbour of the node of the given size containing pos, along direction.
+    /// Returns `OctreeError::NotAnEdge` when the neighbour is outside of the octree, it is then in the adjacent chunk.
+    pub fn neighbour(&self, pos: OctreePosition, size: u8, direction: NeighbourDirection) -> Result<Neighbour<T>, OctreeError> {
+        if size > self.size {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        let node_pos = [pos.0, pos.1, pos.2].map(|coord| coord & !(Octree::octree_size_to_cartestian(size) - 1));
+        let side = Octree::octree_size_to_cartestian(size);
+        let limit = self.cart_size();
+
+        let mut neighbour_pos = [0; 3];
+        for (axis, component) in direction.components().into_iter().enumerate() {
+            neighbour_pos[axis] = match component {
+                0 => node_pos[axis],
+                1 if node_pos[axis] + side < limit => node_pos[axis] + side,
+                -1 if node_pos[axis] >= side => node_pos[axis] - side,
+                _ => return Err(OctreeError::NotAnEdge),
+            };
+        }
+        let neighbour_pos = OctreePosition(neighbour_pos[0], neighbour_pos[1], neighbour_pos[2]);
+
+        Ok(self.neighbour_at(neighbour_pos, size, direction))
+    }
+
+    // Neighbour along direction of a node of the given size, neighbour_pos being in the neighbouring node of the same size.
+    // The queried node can be in another octree, like the octree of the adjacent chunk.
+    pub(super) fn neighbour_at(&self, neighbour_pos: OctreePosition, size: u8, direction: NeighbourDirection) -> Neighbour<T> {
+        let cube = self.get_cube(neighbour_pos, size).unwrap();
+        let cube_mask = !(Octree::octree_size_to_cartestian(cube.size) - 1);
+        let cube_pos = OctreePosition(neighbour_pos.0 & cube_mask, neighbour_pos.1 & cube_mask, neighbour_pos.2 & cube_mask);
+
+        match cube.content {
+            OctreeContent::Voxel(ref voxel) => Neighbour::Leaf(voxel.clone(), cube_pos, cube.size),
+            OctreeContent::Childs(_) => {
+                let mut leaves = Vec::new();
+                cube.push_touching_leaves(cube_pos, direction, &mut leaves);
+                Neighbour::Leaves(leaves)
+            }
+        }
+    }
+
+    // Pushes the leaves on the side of the node facing the opposite of direction, in Morton order
+    fn push_touching_leaves(&self, pos: OctreePosition, direction: NeighbourDirection, leaves: &mut Vec<(T, OctreePosition, u8)>) {
+        match self.content {
+            OctreeContent::Voxel(ref voxel) => leaves.push((voxel.clone(), pos, self.size)),
+            OctreeContent::Childs(ref childs) => {
+                for (indice, child) in childs.iter().enumerate() {
+                    // Bit of the child indice along each axis, 0 on the low side and 1 on the high side
+                    let touches = direction.components().into_iter().enumerate().all(|(axis, component)| {
+                        let high_side = (indice >> axis) & 1 == 1;
+                        match component {
+                            1 => !high_side,
+                            -1 => high_side,
+                            _ => true,
+                        }
+                    });
+                    if touches {
+                        child.push_touching_leaves(pos.child(indice, child.size), direction, leaves);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbour_directions() {
+        assert_eq!(NeighbourDirection::all().count(), 26);
+        assert_eq!(NeighbourDirection::all().filter(|direction| direction.axis_count() == 1).count(), 6);
+        assert_eq!(NeighbourDirection::all().filter(|direction| direction.axis_count() == 2).count(), 12);
+        assert_eq!(NeighbourDirection::all().filter(|direction| direction.axis_count() == 3).count(), 8);
+    }
+
+    #[test]
+    fn same_size_and_larger_neighbours() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(4, 0, 0), 2, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(0, 0, 0), 0, Voxel::DIRT).unwrap();
+
+        assert_eq!(
+            tree.neighbour(OctreePosition(3, 1, 2), 2, NeighbourDirection::POS_X).unwrap(),
+            Neighbour::Leaf(Voxel::STONE, OctreePosition(4, 0, 0), 2)
+        );
+        assert_eq!(
+            tree.neighbour(OctreePosition(0, 0, 0), 0, NeighbourDirection(1, 1, 1)).unwrap(),
+            Neighbour::Leaf(Voxel::EMPTY, OctreePosition(1, 1, 1), 0)
+        );
+        assert_eq!(
+            tree.neighbour(OctreePosition(4, 0, 0), 0, NeighbourDirection::POS_Y).unwrap(),
+            Neighbour::Leaf(Voxel::STONE, OctreePosition(4, 0, 0), 2)
+        );
+        assert_eq!(
+            tree.neighbour(OctreePosition(4, 0, 4), 2, NeighbourDirection::POS_Z).unwrap(),
+            Neighbour::Leaf(Voxel::EMPTY, OctreePosition(0, 0, 8), 3)
+        );
+    }
+
+    #[test]
+    fn smaller_neighbours() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(4, 0, 0), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(7, 0, 0), 0, Voxel::DIRT).unwrap();
+
+        // Face of the split neighbour at x = 4, the dirt voxel at x = 7 does not touch it
+        let leaves = tree.neighbour(OctreePosition(0, 0, 0), 2, NeighbourDirection::POS_X).unwrap().leaves();
+        assert_eq!(leaves.iter().map(|(_, _, size)| Octree::octree_size_to_cartestian(*size).pow(2)).sum::<u64>(), 16);
+        assert!(leaves.iter().all(|(_, pos, _)| pos.0 == 4));
+        assert!(leaves.contains(&(Voxel::STONE, OctreePosition(4, 0, 0), 0)));
+        assert!(!leaves.iter().any(|(voxel, _, _)| *voxel == Voxel::DIRT));
+
+        // Edge of the split neighbour along z, at x = 4 and y = 3
+        let leaves = tree.neighbour(OctreePosition(0, 4, 0), 2, NeighbourDirection(1, -1, 0)).unwrap().leaves();
+        assert_eq!(leaves.iter().map(|(_, _, size)| Octree::octree_size_to_cartestian(*size)).sum::<u64>(), 4);
+        assert!(leaves.iter().all(|(_, pos, size)| pos.0 == 4 && pos.1 + Octree::octree_size_to_cartestian(*size) == 4));
+    }
+
+    #[test]
+    fn neighbour_outside_of_the_tree() {
+        let tree = Octree::new(4, Some(Voxel::EMPTY));
+
+        assert!(matches!(tree.neighbour(OctreePosition(0, 3, 3), 0, NeighbourDirection::NEG_X), Err(OctreeError::NotAnEdge)));
+        assert!(matches!(tree.neighbour(OctreePosition(15, 15, 3), 0, NeighbourDirection(0, 1, 1)), Err(OctreeError::NotAnEdge)));
+        assert!(matches!(tree.neighbour(OctreePosition(0, 0, 0), 4, NeighbourDirection::POS_Y), Err(OctreeError::NotAnEdge)));
+        assert!(matches!(tree.neighbour(OctreePosition(0, 0, 0), 5, NeighbourDirection::POS_Y), Err(OctreeError::SizeLargerThanOctree)));
+    }
+}
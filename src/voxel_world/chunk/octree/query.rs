@@ -0,0 +1,261 @@
+use bevy::math::{DMat4, DVec3, DVec4};
+
+use super::csg::{BoxShape, Overlap, Shape, Sphere};
+use super::{Octree, OctreeContent, OctreePosition, Voxel};
+
+/// Predicate on the payload of the leaves, see `OctreeQuery::where_voxel`
+pub type VoxelPredicate<'a, T> = Box<dyn Fn(&T) -> bool + 'a>;
+
+/// Convex region bounded by planes, `normal.dot(point) + w >= 0` inside each of them. Culls queries, see `OctreeQuery::in_frustum`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [DVec4; 6],
+}
+
+impl Frustum {
+    /// Frustum of a camera, view_projection going from octree coordinates to clip space.
+    /// Uses the reverse Z of bevy, the near plane is at depth 1 and the far plane at depth 0.
+    pub fn from_view_projection(view_projection: DMat4) -> Self {
+        let rows = [view_projection.row(0), view_projection.row(1), view_projection.row(2), view_projection.row(3)];
+
+        Self {
+            planes: [
+                rows[3] + rows[0],
+                rows[3] - rows[0],
+                rows[3] + rows[1],
+                rows[3] - rows[1],
+                rows[3] - rows[2],
+                rows[2],
+            ],
+        }
+    }
+}
+
+impl Shape for Frustum {
+    fn overlap(&self, min: DVec3, max: DVec3) -> Overlap {
+        let mut overlap = Overlap::Inside;
+        for plane in self.planes {
+            let normal = plane.truncate();
+            // Corners of the cube the farthest along and against the normal
+            let positive = DVec3::select(normal.cmpge(DVec3::ZERO), max, min);
+            let negative = DVec3::select(normal.cmpge(DVec3::ZERO), min, max);
+
+            // Like BoxShape, a cube only touching the frustum is outside
+            if normal.dot(positive) + plane.w <= 0. {
+                return Overlap::Outside;
+            }
+            if normal.dot(negative) + plane.w < 0. {
+                overlap = Overlap::Partial;
+            }
+        }
+        overlap
+    }
+
+    fn contains(&self, point: DVec3) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(point) + plane.w >= 0.)
+    }
+}
+
+/// Iterates over the nodes of an octree in Morton order like `OctreeIterator`,
+/// the subtrees outside of the query are skipped without being visited.
+/// Yields leaves, and the nodes cut by `max_depth` which may still have childs.
+pub struct OctreeQuery<'a, T = Voxel> {
+    stack: Vec<(&'a Octree<T>, OctreePosition)>,
+    shape: Option<Box<dyn Shape + 'a>>,
+    // Nodes of this size or smaller are not split, from `max_depth`
+    min_size: u8,
+    predicate: Option<VoxelPredicate<'a, T>>,
+}
+
+impl<'a, T: Clone + Eq> OctreeQuery<'a, T> {
+    pub fn new(octree: &'a Octree<T>) -> Self {
+        let mut stack = Vec::with_capacity(octree.size as usize * 7 + 1);
+        stack.push((octree, OctreePosition(0, 0, 0)));
+
+        Self {
+            stack,
+            shape: None,
+            min_size: 0,
+            predicate: None,
+        }
+    }
+
+    /// Only nodes overlapping shape, in octree coordinates
+    pub fn in_shape(mut self, shape: impl Shape + 'a) -> Self {
+        self.shape = Some(Box::new(shape));
+        self
+    }
+
+    pub fn in_box(self, min: DVec3, max: DVec3) -> Self {
+        self.in_shape(BoxShape { min, max })
+    }
+
+    pub fn in_sphere(self, center: DVec3, radius: f64) -> Self {
+        self.in_shape(Sphere { center, radius })
+    }
+
+    pub fn in_frustum(self, frustum: Frustum) -> Self {
+        self.in_shape(frustum)
+    }
+
+    /// Nodes deeper than depth levels under the root are yielded as a whole instead of their leaves
+    pub fn max_depth(mut self, depth: u8) -> Self {
+        let root_size = self.stack.first().map_or(0, |(octree, _)| octree.size);
+        self.min_size = root_size.saturating_sub(depth);
+        self
+    }
+
+    /// Only leaves whose voxel matches, nodes cut by `max_depth` are kept if one of their leaves inside the shape matches.
+    /// Checking a cut node stops at its first matching leaf, but walks its whole subtree when none matches.
+    pub fn where_voxel(mut self, predicate: impl Fn(&T) -> bool + 'a) -> Self {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    fn overlaps(&self, node: &Octree<T>, pos: OctreePosition) -> bool {
+        let Some(ref shape) = self.shape else {
+            return true;
+        };
+        let min = DVec3::new(pos.0 as f64, pos.1 as f64, pos.2 as f64);
+        shape.overlap(min, min + node.cart_size() as f64) != Overlap::Outside
+    }
+
+    fn matches(&self, node: &Octree<T>, pos: OctreePosition) -> bool {
+        let Some(ref predicate) = self.predicate else {
+            return true;
+        };
+
+        // Depth first like the query itself, the subtrees outside the shape are skipped
+        let mut stack = vec![(node, pos)];
+        while let Some((node, pos)) = stack.pop() {
+            match node.content {
+                OctreeContent::Voxel(ref voxel) => {
+                    if predicate(voxel) {
+                        return true;
+                    }
+                }
+                OctreeContent::Childs(ref childs) => {
+                    for (indice, child) in childs.iter().enumerate().rev() {
+                        let child_pos = pos.child(indice, child.size);
+                        if self.overlaps(child, child_pos) {
+                            stack.push((child.as_ref(), child_pos));
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+impl<'a, T: Clone + Eq> Iterator for OctreeQuery<'a, T> {
+    type Item = (&'a Octree<T>, OctreePosition);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, pos) = self.stack.pop()?;
+            if !self.overlaps(node, pos) {
+                continue;
+            }
+
+            match node.content {
+                OctreeContent::Childs(ref childs) if node.size > self.min_size => {
+                    for (indice, child) in childs.iter().enumerate().rev() {
+                        self.stack.push((child.as_ref(), pos.child(indice, child.size)));
+                    }
+                }
+                _ => {
+                    if self.matches(node, pos) {
+                        return Some((node, pos));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Restricted iteration over the nodes, see `OctreeQuery`
+    pub fn query(&self) -> OctreeQuery<'_, T> {
+        OctreeQuery::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited_octree() -> Octree {
+        let mut tree = Octree::new(5, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(16, 0, 16), 3, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(31, 31, 31), 0, Voxel::DIRT).unwrap();
+        tree
+    }
+
+    #[test]
+    fn unrestricted_query_matches_iterator() {
+        let tree = edited_octree();
+
+        let leaves: Vec<_> = tree.query().map(|(node, pos)| (node.size, pos)).collect();
+        let expected: Vec<_> = tree.voxel_iterator().map(|(_, pos, size)| (size, pos)).collect();
+        assert_eq!(leaves, expected);
+    }
+
+    #[test]
+    fn query_in_box_and_sphere() {
+        let tree = edited_octree();
+
+        let in_box: Vec<_> = tree.query().in_box(DVec3::splat(16.), DVec3::splat(32.)).collect();
+        assert!(in_box.iter().all(|(_, pos)| pos.0 >= 16 && pos.1 >= 16 && pos.2 >= 16));
+        assert_eq!(in_box.iter().map(|(node, _)| node.cart_size().pow(3)).sum::<u64>(), 16u64.pow(3));
+
+        let stone: Vec<_> = tree.query()
+            .in_sphere(DVec3::new(2.5, 5.5, 1.5), 2.)
+            .where_voxel(|voxel| *voxel == Voxel::STONE)
+            .collect();
+        assert_eq!(stone.len(), 1);
+        assert_eq!(stone[0].1, OctreePosition(2, 5, 1));
+
+        assert_eq!(tree.query().in_sphere(DVec3::new(100., 0., 0.), 10.).count(), 0);
+    }
+
+    #[test]
+    fn query_max_depth_and_where_voxel() {
+        let tree = edited_octree();
+
+        let nodes: Vec<_> = tree.query().max_depth(1).collect();
+        assert_eq!(nodes.len(), 8);
+        assert!(nodes.iter().all(|(node, _)| node.size == 4));
+
+        let not_empty: Vec<_> = tree.query()
+            .max_depth(1)
+            .where_voxel(|voxel| *voxel != Voxel::EMPTY)
+            .map(|(_, pos)| pos)
+            .collect();
+        assert_eq!(not_empty, vec![OctreePosition(0, 0, 0), OctreePosition(16, 0, 16), OctreePosition(16, 16, 16)]);
+
+        // The stone voxel is outside of the box, only the dirt voxel at (31, 31, 31) keeps its node
+        let in_box: Vec<_> = tree.query()
+            .in_box(DVec3::splat(8.), DVec3::splat(32.))
+            .max_depth(1)
+            .where_voxel(|voxel| *voxel != Voxel::EMPTY)
+            .map(|(_, pos)| pos)
+            .collect();
+        assert_eq!(in_box, vec![OctreePosition(16, 16, 16)]);
+    }
+
+    #[test]
+    fn query_in_frustum() {
+        let tree = edited_octree();
+
+        // Orthographic camera looking down -Z at the x < 16, y < 16 quarter of the octree
+        let view_projection = DMat4::orthographic_rh(0., 16., 0., 16., 64., -64.);
+        let frustum = Frustum::from_view_projection(view_projection);
+
+        let leaves: Vec<_> = tree.query().in_frustum(frustum).collect();
+        assert!(!leaves.is_empty());
+        assert!(leaves.iter().all(|(_, pos)| pos.0 < 16 && pos.1 < 16));
+        assert!(leaves.iter().any(|(_, pos)| *pos == OctreePosition(2, 5, 1)));
+    }
+}
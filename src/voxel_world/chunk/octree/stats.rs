@@ -0,0 +1,118 @@
+use std::hash::Hash;
+use std::mem::size_of;
+
+use bevy::utils::HashMap;
+
+use super::{Octree, OctreeContent, Voxel};
+
+/// Size and content of an octree, see `Octree::stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OctreeStats<T: Eq + Hash = Voxel> {
+    pub node_count: u64,
+    pub leaf_count: u64,
+    /// Number of leaves holding each payload
+    pub leaves: HashMap<T, u64>,
+    /// Volume in voxels of size 0 of each payload, saturating for octrees larger than 42
+    pub volumes: HashMap<T, u128>,
+    /// Depth of the deepest leaf, 0 when the root is a leaf
+    pub max_depth: u8,
+    /// Estimated heap size of the boxed childs, the heap owned by the payloads is not counted
+    pub heap_bytes: u64,
+}
+
+impl<T: Eq + Hash> Default for OctreeStats<T> {
+    fn default() -> Self {
+        Self {
+            node_count: 0,
+            leaf_count: 0,
+            leaves: HashMap::new(),
+            volumes: HashMap::new(),
+            max_depth: 0,
+            heap_bytes: 0,
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> OctreeStats<T> {
+    /// Volume in voxels of size 0 of the payloads matching predicate
+    pub fn volume(&self, predicate: impl Fn(&T) -> bool) -> u128 {
+        self.volumes.iter()
+            .filter(|(voxel, _)| predicate(voxel))
+            .fold(0, |volume, (_, voxel_volume)| volume.saturating_add(*voxel_volume))
+    }
+
+    /// Adds the numbers of other, to aggregate several octrees
+    pub fn merge(&mut self, other: &OctreeStats<T>) {
+        self.node_count += other.node_count;
+        self.leaf_count += other.leaf_count;
+        for (voxel, count) in other.leaves.iter() {
+            *self.leaves.entry(voxel.clone()).or_insert(0) += count;
+        }
+        for (voxel, volume) in other.volumes.iter() {
+            let total = self.volumes.entry(voxel.clone()).or_insert(0);
+            *total = total.saturating_add(*volume);
+        }
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.heap_bytes += other.heap_bytes;
+    }
+}
+
+impl<T: Clone + Eq + Hash> Octree<T> {
+    pub fn stats(&self) -> OctreeStats<T> {
+        let mut stats = OctreeStats::default();
+        self.add_node_stats(0, &mut stats);
+        stats
+    }
+
+    fn add_node_stats(&self, depth: u8, stats: &mut OctreeStats<T>) {
+        stats.node_count += 1;
+        match self.content {
+            OctreeContent::Voxel(ref voxel) => {
+                stats.leaf_count += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                *stats.leaves.entry(voxel.clone()).or_insert(0) += 1;
+
+                let volume = 1u128.checked_shl(3 * self.size as u32).unwrap_or(u128::MAX);
+                let total = stats.volumes.entry(voxel.clone()).or_insert(0);
+                *total = total.saturating_add(volume);
+            }
+            OctreeContent::Childs(ref childs) => {
+                stats.heap_bytes += 8 * size_of::<Octree<T>>() as u64;
+                for child in childs.iter() {
+                    child.add_node_stats(depth + 1, stats);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::OctreePosition;
+    use super::*;
+
+    #[test]
+    fn octree_stats() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        assert_eq!(tree.stats().node_count, 1);
+        assert_eq!(tree.stats().heap_bytes, 0);
+
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.set_voxel(OctreePosition(8, 8, 8), 3, Voxel::DIRT).unwrap();
+        let stats = tree.stats();
+
+        assert_eq!(stats.node_count, 1 + 4 * 8);
+        assert_eq!(stats.leaf_count, 4 * 7 + 1);
+        assert_eq!(stats.leaves[&Voxel::STONE], 1);
+        assert_eq!(stats.leaves[&Voxel::DIRT], 1);
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(stats.heap_bytes, 4 * 8 * size_of::<Octree>() as u64);
+        assert_eq!(stats.volume(|voxel| *voxel != Voxel::EMPTY), 1 + 512);
+        assert_eq!(stats.volume(|_| true), 16 * 16 * 16);
+
+        let mut merged = stats.clone();
+        merged.merge(&Octree::new(4, Some(Voxel::STONE)).stats());
+        assert_eq!(merged.leaves[&Voxel::STONE], 2);
+        assert_eq!(merged.volumes[&Voxel::STONE], 1 + 4096);
+    }
+}
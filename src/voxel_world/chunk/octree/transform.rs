@@ -0,0 +1,198 @@
+use bevy::math::I64Vec3;
+
+use super::{Octree, OctreeContent, OctreeError, OctreePosition, Voxel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn indice(&self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Mirrors the octree along axis, reordering the childs of every node
+    pub fn mirror(&mut self, axis: Axis) {
+        let bit = 1 << axis.indice();
+        self.permute_childs(&|indice| indice ^ bit);
+    }
+
+    /// Rotates the octree by quarter_turns quarters of a turn around axis, counterclockwise looking down the axis.
+    /// Like `mirror` only the childs of the nodes are reordered.
+    pub fn rotate(&mut self, axis: Axis, quarter_turns: i32) {
+        // Axes turning into each other, u goes to v with a quarter of a turn
+        let a = axis.indice();
+        let (u, v) = ((a + 1) % 3, (a + 2) % 3);
+        let quarter_turn = |indice: usize| {
+            let (bit_u, bit_v) = ((indice >> u) & 1, (indice >> v) & 1);
+            (indice & (1 << a)) | ((1 - bit_v) << u) | (bit_u << v)
+        };
+
+        let turns = quarter_turns.rem_euclid(4);
+        if turns != 0 {
+            self.permute_childs(&|indice| (0..turns).fold(indice, |indice, _| quarter_turn(indice)));
+        }
+    }
+
+    // Moves the child at indice to map(indice) in every node, map must be a bijection of 0..8
+    fn permute_childs(&mut self, map: &impl Fn(usize) -> usize) {
+        self.hash_cache.clear();
+        if let OctreeContent::Childs(ref mut childs) = self.content {
+            let mut targets: [usize; 8] = std::array::from_fn(map);
+            for indice in 0..8 {
+                while targets[indice] != indice {
+                    let target = targets[indice];
+                    childs.swap(indice, target);
+                    targets.swap(indice, target);
+                }
+            }
+
+            for child in childs.iter_mut() {
+                child.permute_childs(map);
+            }
+        }
+    }
+
+    /// Copy of the octree moved by offset nodes of size node_size, the uncovered nodes are set to fill
+    /// and the ones moved out of the octree are dropped. Whole subtrees are copied, leaves are not visited.
+    pub fn translated(&self, node_size: u8, offset: I64Vec3, fill: T) -> Result<Self, OctreeError> {
+        if node_size > self.size {
+            return Err(OctreeError::SizeLargerThanOctree);
+        }
+
+        let shift = offset * Octree::octree_size_to_cartestian(node_size) as i64;
+        Ok(self.translated_node(OctreePosition(0, 0, 0), self.size, node_size, shift, &fill))
+    }
+
+    fn translated_node(&self, pos: OctreePosition, size: u8, node_size: u8, shift: I64Vec3, fill: &T) -> Self {
+        if size > node_size {
+            let mut node = Octree::with_childs(size, std::array::from_fn(|indice| {
+                Box::new(self.translated_node(pos.child(indice, size - 1), size - 1, node_size, shift, fill))
+            }));
+            node.merge_childs();
+            return node;
+        }
+
+        let source = I64Vec3::new(pos.0 as i64, pos.1 as i64, pos.2 as i64) - shift;
+        let limit = self.cart_size() as i64;
+        if source.min_element() < 0 || source.max_element() >= limit {
+            return Octree::filled(size, fill.clone());
+        }
+
+        // Nodes of node_size keep their alignment, the source is a node of the same size or a larger leaf
+        let source = self.get_cube(OctreePosition(source.x as u64, source.y as u64, source.z as u64), size).unwrap();
+        match source.content {
+            OctreeContent::Voxel(ref voxel) => Octree::filled(size, voxel.clone()),
+            OctreeContent::Childs(_) => {
+                let mut copy = source.clone();
+                copy.invalidate_hashes();
+                copy
+            }
+        }
+    }
+}
+
+impl Octree {
+    /// Moves the octree in place by offset nodes of size node_size, see `Octree::translated`
+    pub fn translate(&mut self, node_size: u8, offset: I64Vec3) -> Result<(), OctreeError> {
+        *self = self.translated(node_size, offset, Voxel::EMPTY)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::*;
+
+    fn asymmetric_octree() -> Octree {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.fill_box(DVec3::new(1., 0., 2.), DVec3::new(7., 3., 5.), Voxel::STONE);
+        tree.set_voxel(OctreePosition(9, 12, 3), 0, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(8, 0, 8), 2, Voxel::DIRT).unwrap();
+        tree
+    }
+
+    #[test]
+    fn four_rotations_are_identity() {
+        let tree = asymmetric_octree();
+
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let mut rotated = tree.clone();
+            for _ in 0..4 {
+                rotated.rotate(axis, 1);
+            }
+            assert_eq!(rotated, tree);
+
+            rotated.rotate(axis, 1);
+            assert_ne!(rotated, tree);
+            rotated.rotate(axis, -1);
+            assert_eq!(rotated, tree);
+        }
+    }
+
+    #[test]
+    fn double_mirror_is_identity() {
+        let tree = asymmetric_octree();
+
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let mut mirrored = tree.clone();
+            mirrored.mirror(axis);
+            assert_ne!(mirrored, tree);
+            mirrored.mirror(axis);
+            assert_eq!(mirrored, tree);
+        }
+    }
+
+    #[test]
+    fn rotate_and_mirror_move_voxels() {
+        let tree = asymmetric_octree();
+
+        let mut rotated = tree.clone();
+        rotated.rotate(Axis::Z, 1);
+        // (x, y, z) goes to (15 - y, x, z)
+        assert_eq!(rotated.get_voxel(OctreePosition(3, 9, 3)), Voxel::DIRT);
+        assert_eq!(rotated.voxel_iterator().count(), tree.voxel_iterator().count());
+
+        let mut rotated = tree.clone();
+        rotated.rotate(Axis::Y, 1);
+        // (x, y, z) goes to (z, y, 15 - x)
+        assert_eq!(rotated.get_voxel(OctreePosition(3, 12, 6)), Voxel::DIRT);
+
+        let mut mirrored = tree.clone();
+        mirrored.mirror(Axis::X);
+        assert_eq!(mirrored.get_voxel(OctreePosition(6, 12, 3)), Voxel::DIRT);
+        assert_eq!(mirrored.get_voxel(OctreePosition(14, 0, 2)), Voxel::STONE);
+        assert_eq!(mirrored.get_voxel(OctreePosition(8, 0, 2)), Voxel::EMPTY);
+    }
+
+    #[test]
+    fn translate_by_nodes() {
+        let tree = asymmetric_octree();
+
+        let moved = tree.translated(2, I64Vec3::new(1, 0, 0), Voxel::EMPTY).unwrap();
+        assert_eq!(moved.get_voxel(OctreePosition(13, 12, 3)), Voxel::DIRT);
+        assert_eq!(moved.get_voxel(OctreePosition(12, 0, 8)), Voxel::DIRT);
+        assert_eq!(moved.get_voxel(OctreePosition(5, 0, 2)), Voxel::STONE);
+        assert_eq!(moved.get_voxel(OctreePosition(1, 0, 2)), Voxel::EMPTY);
+        assert_eq!(moved.validate(), Ok(()));
+
+        let mut back = moved.clone();
+        back.translate(2, I64Vec3::new(-1, 0, 0)).unwrap();
+        assert_eq!(back, tree);
+
+        let out = tree.translated(3, I64Vec3::new(0, 2, 0), Voxel::STONE).unwrap();
+        assert_eq!(out, Octree::new(4, Some(Voxel::STONE)));
+        assert!(matches!(tree.translated(5, I64Vec3::ONE, Voxel::EMPTY), Err(OctreeError::SizeLargerThanOctree)));
+    }
+}
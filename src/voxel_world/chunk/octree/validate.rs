@@ -0,0 +1,170 @@
+use super::{Octree, OctreeContent, OctreePosition};
+
+/// Broken invariant found by `Octree::validate`, with the position and size of the faulty node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OctreeValidationError {
+    /// Sizes are at most 63
+    SizeTooLarge { pos: OctreePosition, size: u8 },
+    /// Childs have the size of their parent minus one
+    ChildSizeMismatch { pos: OctreePosition, parent_size: u8, child_size: u8 },
+    /// Nodes of size 0 can't be split
+    SplitVoxel { pos: OctreePosition },
+    /// A canonical octree has no node whose eight childs are the same leaf
+    NotCanonical { pos: OctreePosition, size: u8 },
+}
+
+impl<T: Clone + Eq> Octree<T> {
+    /// Checks the sizes of the nodes and that the octree is canonical
+    pub fn validate(&self) -> Result<(), OctreeValidationError> {
+        self.validate_node(OctreePosition(0, 0, 0), true)
+    }
+
+    /// Checks the sizes of the nodes only, octrees split by hand with `split` are accepted
+    pub fn validate_structure(&self) -> Result<(), OctreeValidationError> {
+        self.validate_node(OctreePosition(0, 0, 0), false)
+    }
+
+    fn validate_node(&self, pos: OctreePosition, canonical: bool) -> Result<(), OctreeValidationError> {
+        self.validate_shallow(pos, canonical)?;
+        if let OctreeContent::Childs(ref childs) = self.content {
+            for (indice, child) in childs.iter().enumerate() {
+                child.validate_node(pos.child(indice, child.size), canonical)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Checks the node and the sizes of its childs, not the descendants of the childs
+    fn validate_shallow(&self, pos: OctreePosition, canonical: bool) -> Result<(), OctreeValidationError> {
+        if self.size > 63 {
+            return Err(OctreeValidationError::SizeTooLarge { pos, size: self.size });
+        }
+        let OctreeContent::Childs(ref childs) = self.content else {
+            return Ok(());
+        };
+        if self.size == 0 {
+            return Err(OctreeValidationError::SplitVoxel { pos });
+        }
+
+        for child in childs.iter() {
+            if child.size + 1 != self.size {
+                return Err(OctreeValidationError::ChildSizeMismatch { pos, parent_size: self.size, child_size: child.size });
+            }
+        }
+
+        if canonical {
+            if let OctreeContent::Voxel(ref voxel) = childs[0].content {
+                if childs.iter().all(|child| matches!(child.content, OctreeContent::Voxel(ref child_voxel) if child_voxel == voxel)) {
+                    return Err(OctreeValidationError::NotCanonical { pos, size: self.size });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges bottom-up every node whose eight childs are the same leaf, returns the number of merged nodes
+    pub fn canonicalize(&mut self) -> usize {
+        let mut merged = 0;
+        if let OctreeContent::Childs(ref mut childs) = self.content {
+            for child in childs.iter_mut() {
+                merged += child.canonicalize();
+            }
+        }
+        if self.merge_childs() {
+            merged += 1;
+        }
+        merged
+    }
+
+    /// Merged octree rebuilt from untrusted nodes, like the ones of a deserialised octree
+    pub fn canonicalized(mut self) -> Result<Self, OctreeValidationError> {
+        self.validate_structure()?;
+        self.canonicalize();
+        Ok(self)
+    }
+
+    //Panics on an invalid octree, only in debug builds
+    pub(crate) fn debug_validate(&self, canonical: bool) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.validate_node(OctreePosition(0, 0, 0), canonical) {
+                panic!("Invalid octree: {error:?}");
+            }
+        }
+    }
+
+    //Like debug_validate on the nodes from the root to the node of size at pos only, cheap enough to run after every edit
+    pub(crate) fn debug_validate_path(&self, pos: OctreePosition, size: u8) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let mut node = self;
+        loop {
+            if let Err(error) = node.validate_shallow(pos.parent(node.size), false) {
+                panic!("Invalid octree: {error:?}");
+            }
+            match node.content {
+                OctreeContent::Childs(ref childs) if node.size > size => node = &childs[pos.get_child_indice(node.size - 1)],
+                _ => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Voxel;
+    use super::*;
+
+    #[test]
+    fn validate_octrees() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        let mut split = tree.clone();
+        split.get_cube_mut(OctreePosition(8, 8, 8), 3).unwrap().split().unwrap();
+        assert_eq!(split.validate_structure(), Ok(()));
+        assert_eq!(split.validate(), Err(OctreeValidationError::NotCanonical { pos: OctreePosition(8, 8, 8), size: 3 }));
+        assert_eq!(split.canonicalize(), 1);
+        assert_eq!(split, tree);
+
+        let mut wrong_size = tree.clone();
+        wrong_size.get_cube_mut(OctreePosition(2, 5, 1), 0).unwrap().size = 2;
+        assert_eq!(
+            wrong_size.validate_structure(),
+            Err(OctreeValidationError::ChildSizeMismatch { pos: OctreePosition(2, 4, 0), parent_size: 1, child_size: 2 })
+        );
+
+        let mut too_large = Octree::new(0, Some(Voxel::EMPTY));
+        too_large.size = 64;
+        assert_eq!(too_large.validate(), Err(OctreeValidationError::SizeTooLarge { pos: OctreePosition(0, 0, 0), size: 64 }));
+    }
+
+    #[test]
+    fn canonicalize_split_octree() {
+        let mut tree = Octree::new(3, Some(Voxel::DIRT));
+        tree.split().unwrap();
+        if let OctreeContent::Childs(childs) = tree.content_mut() {
+            for child in childs.iter_mut() {
+                child.split().unwrap();
+            }
+        }
+
+        assert_eq!(tree.clone().canonicalized(), Ok(Octree::new(3, Some(Voxel::DIRT))));
+        assert_eq!(tree.canonicalize(), 9);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "ChildSizeMismatch")]
+    fn set_voxel_checks_the_edited_path() {
+        let mut tree = Octree::new(4, Some(Voxel::EMPTY));
+        tree.set_voxel(OctreePosition(2, 5, 1), 0, Voxel::STONE).unwrap();
+        tree.get_cube_mut(OctreePosition(2, 5, 1), 0).unwrap().size = 2;
+
+        // Nodes off the edited path are not checked
+        tree.set_voxel(OctreePosition(12, 12, 12), 0, Voxel::DIRT).unwrap();
+        tree.set_voxel(OctreePosition(3, 5, 1), 0, Voxel::DIRT).unwrap();
+    }
+}
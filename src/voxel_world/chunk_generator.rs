@@ -10,18 +10,65 @@ use noise::Perlin;
 
 use self::world_generator::WorldGenerator;
 
-use super::chunk::octree::{self, Octree};
+use super::block_registry::BlockRegistry;
+use super::chunk::octree::Octree;
 use super::chunk::{self, Chunk};
+use super::floating_origin::{FloatingOrigin, FloatingOriginSet, WorldPosition};
+use super::settings::VoxelWorldSettings;
 
-#[derive(Component, PartialEq, Eq, Clone, Copy)]
+#[derive(Component, PartialEq, Eq, Clone, Copy, Debug)]
 pub enum ChunkLoadingStatus {
     GenerationRequested(I64Vec3),
     Loaded,
     DestructionRequested,
 }
 
+/// Sent when the octree of a chunk has been generated and the `Chunk` component inserted.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ChunkLoaded {
+    pub entity: Entity,
+    pub position: I64Vec3,
+    pub old_status: ChunkLoadingStatus,
+    pub new_status: ChunkLoadingStatus,
+}
+
+/// Sent when the mesh of a chunk has been added to the mesh assets or updated, only with the `render` feature.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ChunkMeshed {
+    pub entity: Entity,
+    pub position: I64Vec3,
+    pub old_status: ChunkLoadingStatus,
+    pub new_status: ChunkLoadingStatus,
+}
+
+/// Sent right before a chunk entity is despawned, the entity is still valid when the event is sent.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ChunkUnloading {
+    pub entity: Entity,
+    pub position: I64Vec3,
+    pub old_status: ChunkLoadingStatus,
+    pub new_status: ChunkLoadingStatus,
+}
+
+/// Sent when the `Chunk` component of an already loaded chunk has been mutably accessed.
+/// Modifications don't change the status, `old_status` and `new_status` are always equal.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ChunkModified {
+    pub entity: Entity,
+    pub position: I64Vec3,
+    pub old_status: ChunkLoadingStatus,
+    pub new_status: ChunkLoadingStatus,
+}
+
 #[derive(Component)]
-pub struct ChunkGenerationTask(Task<(Octree, Mesh)>);
+pub struct ChunkGenerationTask(Task<Octree>);
+
+// Inserted along with `ChunkLoadingStatus::DestructionRequested`, chunks still in generation have no `Chunk` to read the position from
+#[derive(Component, Debug, Clone, Copy)]
+struct ChunkDestructionRequest {
+    position: I64Vec3,
+    old_status: ChunkLoadingStatus,
+}
 
 pub struct ChunkGeneratorPlugin;
 
@@ -32,19 +79,28 @@ impl Plugin for ChunkGeneratorPlugin {
                 perlin: Perlin::new(65464),
                 amplitude: 5.,
                 scale: 10.,
-                chunk_octree_size: 10,
-                world_block_ocree_size: 2,
+                ground_block: "stone".to_string(),
             })
+            .add_event::<ChunkLoaded>()
+            .add_event::<ChunkMeshed>()
+            .add_event::<ChunkUnloading>()
+            .add_event::<ChunkModified>()
             .add_systems(Update, (
-                chunk_generator_system,
+                // A generated chunk is Loaded before its Chunk is inserted by the commands, it would be requested again in between
+                chunk_generator_system.before(chunk_generation_system_end_generation),
                 chunk_generation_system_end_generation,
                 chunk_generation_system_start_generation,
-                chunk_destroying_system
-            ))
+                chunk_destroying_system,
+                chunk_modification_system
+            ).in_set(ChunkGenerationSet).after(FloatingOriginSet))
         ;
     }
 }
 
+/// Systems loading, unloading and editing the `Chunk` components, systems reading chunks should run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkGenerationSet;
+
 
 #[derive(Component, Debug)]
 pub struct ChunkGenerator {
@@ -68,28 +124,35 @@ impl ChunkGenerator {
 fn chunk_generator_system(
     mut commands: Commands,
     mut chunks_query: Query<(Entity, &Chunk, & mut ChunkLoadingStatus)>,
-    mut in_generation_query: Query<(Entity, & mut ChunkLoadingStatus), (With<ChunkGenerationTask>, Without<Chunk>)>,
-    generator_query: Query<(&ChunkGenerator, &Transform)>,
+    // Chunks waiting for their generation task to be spawned are in generation as well
+    mut in_generation_query: Query<(Entity, & mut ChunkLoadingStatus), Without<Chunk>>,
+    generator_query: Query<(&ChunkGenerator, &WorldPosition)>,
+    settings: Res<VoxelWorldSettings>,
 ) {
-    for (generator, transform) in generator_query.iter() {
+    if settings.validate().is_err() {
+        return;
+    }
+
+    for (generator, world_position) in generator_query.iter() {
         let mut to_load = HashSet::<I64Vec3>::with_capacity(generator.render_cube_size.pow(3) as usize);
-        let player_pos = chunk::coords_to_chunk_pos(transform.translation);
+        let player_pos = world_position.chunk;
 
         for i in generator.relative_pos_range() {
             for j in generator.relative_pos_range() {
                 for k in generator.relative_pos_range() {
                     to_load.insert(
                         I64Vec3::new(i, j, k) + 
-                        chunk::coords_to_chunk_pos(transform.translation)
+                        player_pos
                     );
                 }
             }
         }
 
-        for (_entity, chunk, mut chunk_loading_status) in chunks_query.iter_mut() {
+        for (entity, chunk, mut chunk_loading_status) in chunks_query.iter_mut() {
             if !generator.chunk_is_in_loading_radius(player_pos, chunk.position) {
                 match *chunk_loading_status {
                     ChunkLoadingStatus::Loaded => {
+                        commands.entity(entity).insert(ChunkDestructionRequest { position: chunk.position, old_status: *chunk_loading_status });
                         *chunk_loading_status = ChunkLoadingStatus::DestructionRequested;
                     }
                     _ => {}
@@ -99,9 +162,10 @@ fn chunk_generator_system(
             }
         }
 
-        for (_entity, mut chunk_loading_status) in in_generation_query.iter_mut() {
+        for (entity, mut chunk_loading_status) in in_generation_query.iter_mut() {
             if let ChunkLoadingStatus::GenerationRequested(pos) = *chunk_loading_status {
                 if !generator.chunk_is_in_loading_radius(player_pos, pos) {
+                    commands.entity(entity).insert(ChunkDestructionRequest { position: pos, old_status: *chunk_loading_status });
                     *chunk_loading_status = ChunkLoadingStatus::DestructionRequested;
                 }else {
                     to_load.remove(&pos);
@@ -120,23 +184,32 @@ fn chunk_generator_system(
 fn chunk_generation_system_start_generation (
     mut commands: Commands,
     generation_requested_chunks_query: Query<(Entity, &ChunkLoadingStatus), Without<ChunkGenerationTask>>,
-    world_generator: Res<WorldGenerator>
+    world_generator: Res<WorldGenerator>,
+    settings: Res<VoxelWorldSettings>,
+    registry: Res<BlockRegistry>
 ) {
+    if let Err(error) = settings.validate() {
+        if settings.is_changed() {
+            error!("Invalid voxel world settings, chunk generation is suspended: {error:?}");
+        }
+        return;
+    }
+    let Some(ground) = registry.voxel(&world_generator.ground_block) else {
+        if world_generator.is_changed() || registry.is_changed() {
+            error!("Unknown ground block {:?}, chunk generation is suspended", world_generator.ground_block);
+        }
+        return;
+    };
+
     let thread_pool = AsyncComputeTaskPool::get();
 
     for (entity, status) in generation_requested_chunks_query.iter() {
         if let ChunkLoadingStatus::GenerationRequested(pos) = *status {
             let generator: WorldGenerator = world_generator.clone();
+            let settings: VoxelWorldSettings = *settings;
 
             let task = thread_pool.spawn(async move {
-            
-                let octree = chunk::generate_octree(pos, &generator).await;
-                let mesh = chunk::generate_mesh(&octree).await;
-                
-                (
-                    octree,
-                    mesh
-                )
+                chunk::generate_octree(pos, &generator, &settings, ground).await
             });
             commands.entity(entity).insert(ChunkGenerationTask(task));
         }
@@ -146,32 +219,36 @@ fn chunk_generation_system_start_generation (
 fn chunk_generation_system_end_generation (
     mut commands: Commands,
     mut in_generation_chunks_query: Query<(Entity, &mut ChunkLoadingStatus, &mut ChunkGenerationTask)>,
-    mut mesh_assets_res: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>
+    settings: Res<VoxelWorldSettings>,
+    origin: Res<FloatingOrigin>,
+    mut loaded_ev: EventWriter<ChunkLoaded>
 ) {
     for (entity, mut status, mut task) in in_generation_chunks_query.iter_mut() {
         match *status {
             ChunkLoadingStatus::GenerationRequested(pos) => {
-                if let Some((tree, mesh)) = block_on(poll_once(&mut task.0)) {
-                    
-                    let mesh_handle = mesh_assets_res.add(mesh);
+                if let Some(tree) = block_on(poll_once(&mut task.0)) {
+                    let world_position = WorldPosition::from_chunk(pos);
                     
                     commands.entity(entity).insert((
+                        world_position,
                         Chunk {
                             octree: tree,
                             position: pos,
-                            mesh: mesh_handle.clone(),
                         },
-                        PbrBundle {
-                            transform: Transform::from_translation(chunk::chunk_pos_to_coords(pos)),
-                            mesh: mesh_handle.clone_weak(),
-                            material: materials.add(Color::rgb_u8(124, 144, 255)),
-                            ..default()
-                        },
-                
+                        TransformBundle::from_transform(
+                            Transform::from_translation(world_position.render_translation(&settings, origin.chunk))
+                        ),
                     ));
+                    let old_status = *status;
                     *status = ChunkLoadingStatus::Loaded;
                     commands.entity(entity).remove::<ChunkGenerationTask>();
+
+                    loaded_ev.send(ChunkLoaded {
+                        entity,
+                        position: pos,
+                        old_status,
+                        new_status: *status,
+                    });
                 }
             },
             _=>{}
@@ -181,12 +258,143 @@ fn chunk_generation_system_end_generation (
 
 fn chunk_destroying_system(
     mut commands: Commands,
-    chunks_query: Query<(Entity, &ChunkLoadingStatus), With<Chunk>>,
+    // Loaded chunks as well as chunks still in generation, despawning them drops their task which cancels it
+    chunks_query: Query<(Entity, &ChunkDestructionRequest, &ChunkLoadingStatus)>,
+    mut unloading_ev: EventWriter<ChunkUnloading>
 ) {
-    for (entity, status) in chunks_query.iter() {
+    for (entity, request, status) in chunks_query.iter() {
         if status == &ChunkLoadingStatus::DestructionRequested {
+            unloading_ev.send(ChunkUnloading {
+                entity,
+                position: request.position,
+                old_status: request.old_status,
+                new_status: *status,
+            });
             commands.entity(entity).despawn_recursive();
         }
     }
 }
 
+fn chunk_modification_system(
+    chunks_query: Query<(Entity, Ref<Chunk>, &ChunkLoadingStatus), Changed<Chunk>>,
+    mut modified_ev: EventWriter<ChunkModified>
+) {
+    for (entity, chunk, status) in chunks_query.iter() {
+        if !chunk.is_added() {
+            modified_ev.send(ChunkModified {
+                entity,
+                position: chunk.position,
+                old_status: *status,
+                new_status: *status,
+            });
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use crate::voxel_world::VoxelWorld;
+
+    use super::*;
+
+    // Updates a headless voxel world with a chunk generator at the origin until done returns true,
+    // false if it never does. The generation tasks run on other threads, so updates are spaced out
+    pub(crate) fn update_headless_world_until(mut done: impl FnMut(&mut App) -> bool) -> bool {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, VoxelWorld))
+            .world.spawn((
+                ChunkGenerator {
+                    render_cube_size: 2,
+                },
+                WorldPosition::default(),
+            ));
+
+        for _ in 0..1000 {
+            app.update();
+            if done(&mut app) {
+                return true;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn headless_chunk_generation() {
+        let mut loaded_reader = ManualEventReader::<ChunkLoaded>::default();
+        let mut loaded_count = 0;
+
+        let generated = update_headless_world_until(|app| {
+            loaded_count += loaded_reader.read(app.world.resource::<Events<ChunkLoaded>>()).count();
+
+            let mut chunks_query = app.world.query::<(&Chunk, &ChunkLoadingStatus)>();
+            if chunks_query.iter(&app.world).count() != 8 {
+                return false;
+            }
+            for (_chunk, status) in chunks_query.iter(&app.world) {
+                assert_eq!(*status, ChunkLoadingStatus::Loaded);
+            }
+            assert_eq!(loaded_count, 8);
+            true
+        });
+
+        assert!(generated, "Chunks were not generated without rendering");
+    }
+
+    #[test]
+    fn request_chunks_once() {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, VoxelWorld))
+            .world.spawn((
+                ChunkGenerator {
+                    render_cube_size: 2,
+                },
+                WorldPosition::default(),
+            ));
+
+        // The second update spawns the generation tasks of the chunks requested by the first one, they must not be requested again
+        for _ in 0..3 {
+            app.update();
+            assert_eq!(app.world.query::<&ChunkLoadingStatus>().iter(&app.world).count(), 8);
+        }
+    }
+
+    #[test]
+    fn unload_chunks_in_generation() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, VoxelWorld));
+        let generator = app.world.spawn((
+            ChunkGenerator {
+                render_cube_size: 2,
+            },
+            WorldPosition::default(),
+        )).id();
+
+        // The chunks are requested, then left behind before their generation tasks are even spawned
+        app.update();
+        let requested: Vec<Entity> = app.world.query::<(Entity, &ChunkLoadingStatus)>().iter(&app.world).map(|(entity, _)| entity).collect();
+        assert_eq!(requested.len(), 8);
+        app.world.get_mut::<WorldPosition>(generator).unwrap().chunk = I64Vec3::splat(100);
+
+        let mut unloading_reader = ManualEventReader::<ChunkUnloading>::default();
+        let mut unloading = Vec::new();
+        for _ in 0..3 {
+            app.update();
+            unloading.extend(unloading_reader.read(app.world.resource::<Events<ChunkUnloading>>()).copied());
+        }
+
+        assert_eq!(unloading.len(), 8);
+        for event in unloading {
+            assert!(requested.contains(&event.entity));
+            assert_eq!(event.old_status, ChunkLoadingStatus::GenerationRequested(event.position));
+            assert_eq!(event.new_status, ChunkLoadingStatus::DestructionRequested);
+        }
+        assert!(requested.iter().all(|entity| app.world.get_entity(*entity).is_none()));
+    }
+}
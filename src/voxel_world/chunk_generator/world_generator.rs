@@ -7,8 +7,8 @@ pub struct WorldGenerator {
     pub perlin: Perlin,
     pub amplitude: f32,
     pub scale: f32,
-    pub chunk_octree_size: u8,
-    pub world_block_ocree_size: u8,
+    /// Registry name of the block filling the ground
+    pub ground_block: String,
 }
 
 impl WorldGenerator {
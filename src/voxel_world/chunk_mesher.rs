@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+use super::block_registry::BlockRegistry;
+use super::chunk::{self, Chunk};
+use super::chunk_generator::{ChunkLoadingStatus, ChunkMeshed};
+use super::settings::VoxelWorldSettings;
+
+/// Builds and updates the meshes of the loaded chunks, only available with the `render` feature.
+pub struct ChunkMesherPlugin;
+
+impl Plugin for ChunkMesherPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                chunk_meshing_system_start_meshing,
+                chunk_meshing_system_end_meshing
+            ).chain());
+    }
+}
+
+#[derive(Component)]
+pub struct ChunkMeshingTask(Task<Mesh>);
+
+fn chunk_meshing_system_start_meshing(
+    mut commands: Commands,
+    changed_chunks_query: Query<(Entity, &Chunk), Changed<Chunk>>,
+    settings: Res<VoxelWorldSettings>,
+    registry: Res<BlockRegistry>,
+    // Copy of the registry shared by the meshing tasks, only cloned again when the registry changes
+    mut shared_registry: Local<Option<Arc<BlockRegistry>>>,
+    mesh_assets_res: Option<Res<Assets<Mesh>>>
+) {
+    // Without mesh assets (e.g. with MinimalPlugins) the world is simulated without being rendered
+    if mesh_assets_res.is_none() {
+        return;
+    }
+
+    if shared_registry.is_none() || registry.is_changed() {
+        *shared_registry = Some(Arc::new(registry.clone()));
+    }
+    let shared_registry = shared_registry.as_ref().unwrap();
+
+    let thread_pool = AsyncComputeTaskPool::get();
+
+    for (entity, chunk) in changed_chunks_query.iter() {
+        let octree = chunk.octree.clone();
+        let settings: VoxelWorldSettings = *settings;
+        let registry = shared_registry.clone();
+
+        let task = thread_pool.spawn(async move {
+            chunk::generate_mesh(&octree, &settings, &registry).await
+        });
+        // Replacing a running task drops it, so only the mesh of the latest octree is kept
+        commands.entity(entity).insert(ChunkMeshingTask(task));
+    }
+}
+
+fn chunk_meshing_system_end_meshing(
+    mut commands: Commands,
+    mut in_meshing_chunks_query: Query<(Entity, &Chunk, &ChunkLoadingStatus, &mut ChunkMeshingTask, Option<&Handle<Mesh>>)>,
+    mesh_assets_res: Option<ResMut<Assets<Mesh>>>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
+    mut meshed_ev: EventWriter<ChunkMeshed>
+) {
+    let (Some(mut mesh_assets_res), Some(mut materials)) = (mesh_assets_res, materials) else {
+        return;
+    };
+
+    for (entity, chunk, status, mut task, mesh_handle) in in_meshing_chunks_query.iter_mut() {
+        if let Some(mesh) = block_on(poll_once(&mut task.0)) {
+            match mesh_handle {
+                Some(mesh_handle) => {
+                    mesh_assets_res.insert(mesh_handle, mesh);
+                }
+                None => {
+                    commands.entity(entity).insert((
+                        mesh_assets_res.add(mesh),
+                        materials.add(Color::rgb_u8(124, 144, 255)),
+                        VisibilityBundle::default(),
+                    ));
+                }
+            }
+            commands.entity(entity).remove::<ChunkMeshingTask>();
+
+            meshed_ev.send(ChunkMeshed {
+                entity,
+                position: chunk.position,
+                old_status: *status,
+                new_status: *status,
+            });
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+use super::block_registry::BlockRegistry;
+use super::chunk::octree::stats::OctreeStats;
+use super::chunk::Chunk;
+use super::chunk_generator::ChunkGenerationSet;
+use super::settings::VoxelWorldSettings;
+
+pub const CHUNK_COUNT: DiagnosticPath = DiagnosticPath::const_new("voxel_world/chunks");
+pub const OCTREE_NODES: DiagnosticPath = DiagnosticPath::const_new("voxel_world/octree_nodes");
+pub const OCTREE_LEAVES: DiagnosticPath = DiagnosticPath::const_new("voxel_world/octree_leaves");
+pub const OCTREE_MAX_DEPTH: DiagnosticPath = DiagnosticPath::const_new("voxel_world/octree_max_depth");
+pub const OCTREE_HEAP_BYTES: DiagnosticPath = DiagnosticPath::const_new("voxel_world/octree_heap_bytes");
+/// Solid volume in voxels of size 0
+pub const SOLID_VOXELS: DiagnosticPath = DiagnosticPath::const_new("voxel_world/solid_voxels");
+/// Solid volume in world units
+pub const SOLID_VOLUME: DiagnosticPath = DiagnosticPath::const_new("voxel_world/solid_volume");
+
+/// Measures the octrees of the loaded chunks, the numbers are summed over all the `Chunk` entities.
+pub struct VoxelWorldDiagnosticsPlugin;
+
+impl Plugin for VoxelWorldDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_diagnostic(Diagnostic::new(CHUNK_COUNT))
+            .register_diagnostic(Diagnostic::new(OCTREE_NODES))
+            .register_diagnostic(Diagnostic::new(OCTREE_LEAVES))
+            .register_diagnostic(Diagnostic::new(OCTREE_MAX_DEPTH))
+            .register_diagnostic(Diagnostic::new(OCTREE_HEAP_BYTES).with_suffix(" B"))
+            .register_diagnostic(Diagnostic::new(SOLID_VOXELS))
+            .register_diagnostic(Diagnostic::new(SOLID_VOLUME))
+            .add_systems(Update, (
+                chunk_stats_system,
+                chunk_diagnostics_system
+            ).chain().after(ChunkGenerationSet));
+    }
+}
+
+/// Stats of the octree of a chunk, updated when the `Chunk` component changes.
+#[derive(Component, Debug, Clone)]
+pub struct ChunkStats(pub OctreeStats);
+
+fn chunk_stats_system(
+    mut commands: Commands,
+    changed_chunks_query: Query<(Entity, &Chunk), Changed<Chunk>>
+) {
+    for (entity, chunk) in changed_chunks_query.iter() {
+        commands.entity(entity).insert(ChunkStats(chunk.octree.stats()));
+    }
+}
+
+fn chunk_diagnostics_system(
+    mut diagnostics: Diagnostics,
+    chunks_query: Query<(&Chunk, &ChunkStats)>,
+    registry: Res<BlockRegistry>,
+    settings: Res<VoxelWorldSettings>
+) {
+    let mut total = OctreeStats::default();
+    let mut solid_volume = 0.;
+
+    for (chunk, ChunkStats(stats)) in chunks_query.iter() {
+        total.merge(stats);
+
+        let voxel_size = settings.chunk_size as f64 / chunk.octree.cart_size() as f64;
+        let solid_voxels = stats.volume(|voxel| registry.get(*voxel).is_some_and(|block| block.solid));
+        solid_volume += solid_voxels as f64 * voxel_size.powi(3);
+    }
+    let solid_voxels = total.volume(|voxel| registry.get(*voxel).is_some_and(|block| block.solid));
+
+    diagnostics.add_measurement(&CHUNK_COUNT, || chunks_query.iter().len() as f64);
+    diagnostics.add_measurement(&OCTREE_NODES, || total.node_count as f64);
+    diagnostics.add_measurement(&OCTREE_LEAVES, || total.leaf_count as f64);
+    diagnostics.add_measurement(&OCTREE_MAX_DEPTH, || total.max_depth as f64);
+    diagnostics.add_measurement(&OCTREE_HEAP_BYTES, || total.heap_bytes as f64);
+    diagnostics.add_measurement(&SOLID_VOXELS, || solid_voxels as f64);
+    diagnostics.add_measurement(&SOLID_VOLUME, || solid_volume);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::diagnostic::DiagnosticsStore;
+
+    use crate::voxel_world::chunk_generator::tests::update_headless_world_until;
+
+    use super::*;
+
+    #[test]
+    fn chunk_diagnostics() {
+        let measured = update_headless_world_until(|app| {
+            let diagnostics = app.world.resource::<DiagnosticsStore>();
+            if diagnostics.get(&CHUNK_COUNT).and_then(Diagnostic::value) != Some(8.) {
+                return false;
+            }
+            assert!(diagnostics.get(&OCTREE_NODES).and_then(Diagnostic::value).unwrap() >= 8.);
+            assert!(diagnostics.get(&SOLID_VOXELS).and_then(Diagnostic::value).unwrap() > 0.);
+            assert!(diagnostics.get(&SOLID_VOLUME).and_then(Diagnostic::value).unwrap() > 0.);
+            true
+        });
+
+        assert!(measured, "Chunk diagnostics were not measured");
+    }
+}
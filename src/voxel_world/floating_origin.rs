@@ -0,0 +1,183 @@
+use bevy::{math::I64Vec3, prelude::*};
+
+use super::{chunk, settings::VoxelWorldSettings};
+
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FloatingOrigin>()
+            .add_systems(Update, (
+                sync_world_positions,
+                rebase_floating_origin
+            ).chain().in_set(FloatingOriginSet));
+    }
+}
+
+/// Systems keeping `WorldPosition` and `Transform` in sync, systems reading `WorldPosition` should run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FloatingOriginSet;
+
+/// Chunk rendered at the origin of the bevy world space, every `Transform` is relative to it.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct FloatingOrigin {
+    pub chunk: I64Vec3,
+    /// Distance in chunks on any axis between the camera and the origin that triggers a rebase
+    pub rebase_chunk_distance: i64,
+}
+
+impl Default for FloatingOrigin {
+    fn default() -> Self {
+        Self {
+            chunk: I64Vec3::ZERO,
+            rebase_chunk_distance: 8,
+        }
+    }
+}
+
+/// Marks the entity the floating origin follows.
+#[derive(Component, Debug, Default)]
+pub struct FloatingOriginCamera;
+
+/// Authoritative position of an entity: the chunk it is in and its offset inside that chunk.
+/// The `Transform` of the entity is rebuilt from it when the floating origin moves.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct WorldPosition {
+    pub chunk: I64Vec3,
+    pub offset: Vec3,
+}
+
+impl WorldPosition {
+    pub fn from_chunk(chunk: I64Vec3) -> Self {
+        Self {
+            chunk,
+            offset: Vec3::ZERO,
+        }
+    }
+
+    pub fn from_render_translation(settings: &VoxelWorldSettings, origin: I64Vec3, translation: Vec3) -> Self {
+        let relative_chunk = chunk::coords_to_chunk_pos(settings, translation);
+
+        Self {
+            chunk: origin.wrapping_add(relative_chunk),
+            offset: translation - chunk::chunk_pos_to_coords(settings, relative_chunk),
+        }
+    }
+
+    pub fn render_translation(&self, settings: &VoxelWorldSettings, origin: I64Vec3) -> Vec3 {
+        chunk::chunk_pos_to_coords(settings, self.chunk.wrapping_sub(origin)) + self.offset
+    }
+}
+
+fn sync_world_positions(
+    settings: Res<VoxelWorldSettings>,
+    origin: Res<FloatingOrigin>,
+    mut query: Query<(&Transform, &mut WorldPosition), Changed<Transform>>
+) {
+    for (transform, mut world_position) in query.iter_mut() {
+        // Transforms written by the floating origin itself match exactly and must not be converted back
+        if world_position.render_translation(&settings, origin.chunk) != transform.translation {
+            *world_position = WorldPosition::from_render_translation(&settings, origin.chunk, transform.translation);
+        }
+    }
+}
+
+fn rebase_floating_origin(
+    settings: Res<VoxelWorldSettings>,
+    mut origin: ResMut<FloatingOrigin>,
+    camera_query: Query<&WorldPosition, With<FloatingOriginCamera>>,
+    mut query: Query<(&mut Transform, &WorldPosition)>
+) {
+    if let Ok(camera_position) = camera_query.get_single() {
+        // unsigned_abs as the wrapped difference can be i64::MIN
+        let distance = camera_position.chunk.wrapping_sub(origin.chunk).to_array().map(i64::unsigned_abs).into_iter().max().unwrap();
+        if distance as i128 > origin.rebase_chunk_distance as i128 {
+            origin.chunk = camera_position.chunk;
+        }
+    }
+
+    if origin.is_changed() {
+        for (mut transform, world_position) in query.iter_mut() {
+            transform.translation = world_position.render_translation(&settings, origin.chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, FloatingOriginPlugin)).init_resource::<VoxelWorldSettings>();
+        app
+    }
+
+    #[test]
+    fn render_translation_round_trip() {
+        let settings = VoxelWorldSettings::default();
+        let cases = [
+            (I64Vec3::splat(i64::MAX), I64Vec3::splat(i64::MAX - 2)),
+            (I64Vec3::splat(i64::MIN), I64Vec3::splat(i64::MIN + 3)),
+            // Origin on the other side of the wrap
+            (I64Vec3::splat(i64::MAX), I64Vec3::splat(i64::MIN + 1)),
+            (I64Vec3::new(i64::MIN, i64::MAX, 0), I64Vec3::new(i64::MAX, i64::MIN, 1)),
+        ];
+
+        for (chunk, origin) in cases {
+            let position = WorldPosition { chunk, offset: Vec3::new(1.5, 2.25, 9.75) };
+            let translation = position.render_translation(&settings, origin);
+            assert!(translation.abs().max_element() < 30., "{translation} for {chunk} from {origin}");
+            assert_eq!(WorldPosition::from_render_translation(&settings, origin, translation), position);
+        }
+    }
+
+    #[test]
+    fn rebase_to_the_camera() {
+        let mut app = app();
+        let settings = VoxelWorldSettings::default();
+        let camera_position = WorldPosition { chunk: I64Vec3::new(i64::MIN, 3, 0), offset: Vec3::new(1., 2., 3.) };
+        let other_position = WorldPosition { chunk: I64Vec3::new(i64::MAX, 3, 1), offset: Vec3::ZERO };
+        let camera = app.world.spawn((
+            FloatingOriginCamera,
+            camera_position,
+            Transform::from_translation(camera_position.render_translation(&settings, I64Vec3::ZERO)),
+        )).id();
+        let other = app.world.spawn((
+            other_position,
+            Transform::from_translation(other_position.render_translation(&settings, I64Vec3::ZERO)),
+        )).id();
+
+        // The difference with the origin is i64::MIN on x
+        app.update();
+
+        assert_eq!(app.world.resource::<FloatingOrigin>().chunk, camera_position.chunk);
+        assert_eq!(app.world.get::<WorldPosition>(camera), Some(&camera_position));
+        assert_eq!(app.world.get::<Transform>(camera).unwrap().translation, Vec3::new(1., 2., 3.));
+        assert_eq!(app.world.get::<Transform>(other).unwrap().translation, Vec3::new(-10., 0., 10.));
+    }
+
+    #[test]
+    fn no_rebase_near_the_origin() {
+        let mut app = app();
+        // 8 chunks away on y, the rebase distance
+        app.world.spawn((FloatingOriginCamera, WorldPosition::default(), Transform::from_xyz(75., -80., 0.)));
+        app.update();
+
+        assert_eq!(app.world.resource::<FloatingOrigin>().chunk, I64Vec3::ZERO);
+    }
+
+    #[test]
+    fn rebase_past_threshold() {
+        let mut app = app();
+        app.world.spawn((FloatingOriginCamera, WorldPosition::default(), Transform::from_xyz(75., -80., 0.)));
+        app.update();
+
+        let mut query = app.world.query::<&mut Transform>();
+        query.single_mut(&mut app.world).translation.x = 95.;
+        app.update();
+
+        assert_eq!(app.world.resource::<FloatingOrigin>().chunk, I64Vec3::new(9, -8, 0));
+    }
+}
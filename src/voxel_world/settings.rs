@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use super::chunk::octree::Octree;
+
+/// Biggest accepted difference between the chunk octree size and the block octree size,
+/// the height map of a chunk holds `2^delta * 2^delta` values.
+pub const MAX_CHUNK_BLOCK_SIZE_DELTA: u8 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoxelWorldSettingsError {
+    InvalidChunkSize(f32),
+    ChunkOctreeTooLarge(u8),
+    BlockLargerThanChunk { chunk_octree_size: u8, world_block_octree_size: u8 },
+    TooManyBlocksPerChunk(u8),
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct VoxelWorldSettings {
+    /// Side of a chunk in world units
+    pub chunk_size: f32,
+    /// Octree size of a chunk, a chunk is `2^chunk_octree_size` voxels of size 0 wide
+    pub chunk_octree_size: u8,
+    /// Octree size of a generated block, blocks are `2^(chunk_octree_size - world_block_octree_size)` per chunk side
+    pub world_block_octree_size: u8,
+}
+
+impl Default for VoxelWorldSettings {
+    fn default() -> Self {
+        Self {
+            chunk_size: 10.,
+            chunk_octree_size: 10,
+            world_block_octree_size: 2,
+        }
+    }
+}
+
+impl VoxelWorldSettings {
+    pub fn validate(&self) -> Result<(), VoxelWorldSettingsError> {
+        if !self.chunk_size.is_finite() || self.chunk_size <= 0. {
+            return Err(VoxelWorldSettingsError::InvalidChunkSize(self.chunk_size));
+        }
+        if self.chunk_octree_size > 63 {
+            return Err(VoxelWorldSettingsError::ChunkOctreeTooLarge(self.chunk_octree_size));
+        }
+        if self.world_block_octree_size > self.chunk_octree_size {
+            return Err(VoxelWorldSettingsError::BlockLargerThanChunk { 
+                chunk_octree_size: self.chunk_octree_size, 
+                world_block_octree_size: self.world_block_octree_size 
+            });
+        }
+        if self.block_size_delta() > MAX_CHUNK_BLOCK_SIZE_DELTA {
+            return Err(VoxelWorldSettingsError::TooManyBlocksPerChunk(self.block_size_delta()));
+        }
+
+        Ok(())
+    }
+
+    /// Number of octree levels between a chunk and a generated block
+    pub fn block_size_delta(&self) -> u8 {
+        self.chunk_octree_size - self.world_block_octree_size
+    }
+
+    /// Side of a generated block in world units
+    pub fn block_size(&self) -> f32 {
+        self.chunk_size / Octree::octree_size_to_cartestian(self.block_size_delta()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_settings_are_valid() {
+        assert_eq!(VoxelWorldSettings::default().validate(), Ok(()));
+        assert_eq!(VoxelWorldSettings::default().block_size(), 10. / 256.);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let settings = VoxelWorldSettings { chunk_size: 0., ..default() };
+        assert_eq!(settings.validate(), Err(VoxelWorldSettingsError::InvalidChunkSize(0.)));
+
+        let settings = VoxelWorldSettings { chunk_octree_size: 64, world_block_octree_size: 60, ..default() };
+        assert_eq!(settings.validate(), Err(VoxelWorldSettingsError::ChunkOctreeTooLarge(64)));
+
+        let settings = VoxelWorldSettings { chunk_octree_size: 2, world_block_octree_size: 3, ..default() };
+        assert_eq!(settings.validate(), Err(VoxelWorldSettingsError::BlockLargerThanChunk { chunk_octree_size: 2, world_block_octree_size: 3 }));
+
+        let settings = VoxelWorldSettings { chunk_octree_size: 20, world_block_octree_size: 0, ..default() };
+        assert_eq!(settings.validate(), Err(VoxelWorldSettingsError::TooManyBlocksPerChunk(20)));
+    }
+}